@@ -0,0 +1,6 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|cipher_text: &str| {
+    let _ = joplin_reader::note::decrypt_chunks(cipher_text, "fuzz");
+});
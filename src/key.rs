@@ -1,7 +1,8 @@
+use crate::note::JoplinEncryptionMethod;
+use crate::store::{FsStore, NoteStore};
 use crate::JoplinReaderError;
 
-use std::fs;
-use std::io::{prelude::*, BufReader};
+use std::io::BufRead;
 use std::path::Path;
 
 use sjcl::decrypt_raw;
@@ -16,19 +17,27 @@ pub fn load_master_key(
     key_id: String,
     passphrase: String,
 ) -> Result<MasterKey, JoplinReaderError> {
-    let file = match fs::File::open(key_path) {
-        Ok(file) => file,
-        Err(_) => {
-            return Err(JoplinReaderError::FileReadError {
-                message: "Failed to open file".to_string(),
-            })
-        }
-    };
-    let reader = BufReader::new(file);
+    load_master_key_from_store(
+        &FsStore::default(),
+        &key_path.to_string_lossy(),
+        key_id,
+        passphrase,
+    )
+}
+
+/// Same as [`load_master_key`], but reads the key file from a [`NoteStore`].
+pub fn load_master_key_from_store<S: NoteStore>(
+    store: &S,
+    key_path: &str,
+    key_id: String,
+    passphrase: String,
+) -> Result<MasterKey, JoplinReaderError> {
+    let file = store.read_file(key_path)?;
 
     let mut id: Option<String> = None;
     let mut content: Option<String> = None;
-    for line in reader.lines() {
+    let mut encryption_method: Option<u8> = None;
+    for line in file.as_slice().lines() {
         let line = match line {
             Ok(line) => line,
             Err(_) => {
@@ -37,32 +46,46 @@ pub fn load_master_key(
                 })
             }
         };
-        let mut iter = line.splitn(2, ":");
+        let mut iter = line.splitn(2, ':');
         let key = iter.next();
         let value = iter.next();
         if let (Some(key), Some(value)) = (key, value) {
             match key {
-                "id" => id = Some(value.to_string().trim().to_string()),
+                "id" => id = Some(value.trim().to_string()),
                 "content" => content = Some(value.to_string()),
+                "encryption_method" => encryption_method = value.trim().parse::<u8>().ok(),
                 _ => { /*println!("Unsupported key: {}", key);*/ }
             };
         }
     }
-    if let None = id {
-        return Err(JoplinReaderError::FileReadError {
-            message: "No `id` specified in key".to_string(),
-        });
-    }
-    if let None = content {
-        return Err(JoplinReaderError::FileReadError {
-            message: "No `content` specified in key".to_string(),
-        });
-    }
-    let id = id.unwrap();
-    let content = content.unwrap();
+    let id = match id {
+        Some(id) => id,
+        None => {
+            return Err(JoplinReaderError::FileReadError {
+                message: "No `id` specified in key".to_string(),
+            })
+        }
+    };
+    let content = match content {
+        Some(content) => content,
+        None => {
+            return Err(JoplinReaderError::FileReadError {
+                message: "No `content` specified in key".to_string(),
+            })
+        }
+    };
     if id != key_id {
         return Err(JoplinReaderError::KeyIdMismatch);
     }
+    // Keys without a method are older than the property and always valid
+    if let Some(method) = encryption_method {
+        if !JoplinEncryptionMethod::from(method).is_key_encryption() {
+            return Err(JoplinReaderError::UnexpectedEncryptionMethod {
+                expected: JoplinEncryptionMethod::MethodSjcl4 as u8,
+                found: method,
+            });
+        }
+    }
 
     let plaintext = match decrypt_raw(content, passphrase) {
         Ok(pt) => pt,
@@ -72,5 +95,36 @@ pub fn load_master_key(
             });
         }
     };
-    Ok(String::from_utf8(plaintext).unwrap())
+    // A wrong passphrase may decrypt to garbage instead of failing
+    match String::from_utf8(plaintext) {
+        Ok(master_key) => Ok(master_key),
+        Err(_) => Err(JoplinReaderError::DecryptionError {
+            message: "Master key is not valid UTF-8".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    #[test]
+    fn master_keys_need_a_key_encryption_method() {
+        let mut store = MemoryStore::new();
+        store.insert(
+            "key.md",
+            "id: 3336eb7a2472d9ae4a690a978fa8a46f\ncontent: {}\nencryption_method: 5\ntype_: 9",
+        );
+        let key = load_master_key_from_store(
+            &store,
+            "key.md",
+            "3336eb7a2472d9ae4a690a978fa8a46f".to_string(),
+            "password".to_string(),
+        );
+        match key {
+            Err(JoplinReaderError::UnexpectedEncryptionMethod { expected: 4, found: 5 }) => {}
+            other => panic!("Expected an unexpected encryption method, got {:?}", other),
+        }
+    }
 }
@@ -4,35 +4,73 @@
 //! ## Usage
 //!
 //! Decrypt a file loaded into a string:
-//! ```rust
-//! use joplin_reader::notebook::JoplinNotebook;
-//! 
-//! # fn main() -> Result<(), SjclError> {
+//! ```rust,no_run
+//! use joplin_reader::{JoplinNotebook, JoplinReaderError};
+//!
+//! # fn main() -> Result<(), JoplinReaderError> {
 //! let joplin_folder = "./Joplin";
 //! // I usually take a ';'-separated list of id,password pairs.
 //! let passwords = "3336eb7a2472d9ae4a690a978fa8a46f,plaintext_password".split(";");
-//! let notebooks = JoplinNotebook::new(joplin_folder, passwords)?;
+//! let mut notebooks = JoplinNotebook::new(joplin_folder, passwords)?;
 //! println!("{:?}", notebooks.read_note("9a20a9e4d336de70cb6d22a58a3e673c"));
 //! # Ok(())
 //! # }
 //! ```
 //!
+//! Items can also be read from somewhere else than the local filesystem by
+//! implementing [`store::NoteStore`] and passing it to
+//! [`notebook::JoplinNotebook::with_store`].
+//!
 
 pub mod key;
 pub mod note;
 pub mod notebook;
+pub mod store;
+#[cfg(feature = "sync-info")]
+pub mod sync_info;
+
+// The types needed for most uses are re-exported at the crate root, so
+// `use joplin_reader::JoplinNotebook;` is enough to get started.
+pub use crate::key::MasterKey;
+pub use crate::note::{
+    AlarmInfo, JoplinItemType, LineEnding, MarkupLanguage, NoteInfo, NoteProperties, ReadOptions,
+};
+pub use crate::notebook::{
+    BundleItem, HealthReport, JoplinNotebook, JoplinNotebookBuilder, NoteBundle, NoteQuery,
+    NoteStats, NotebookSummary, ReadAllResult, ResourceInfo, SavedSearch, SearchResult,
+    DEFAULT_IGNORED_FILES,
+};
+pub use crate::store::{FsStore, MemoryStore, MultiStore, NoteStore};
+// The Markdown events are part of the public interface
+#[cfg(feature = "markdown")]
+pub use pulldown_cmark;
 
 use thiserror::Error;
+/// Errors which can occur while reading a Joplin data folder.
 #[derive(Error, Debug)]
 pub enum JoplinReaderError {
     #[error("Failed to read joplin folder")]
     FolderReadError,
+    #[error("Joplin folder `{path:?}` does not exist")]
+    FolderNotFound { path: String },
+    #[error("Permission denied to read joplin folder `{path:?}`")]
+    FolderPermissionDenied { path: String },
+    #[error("`{path:?}` is not a folder")]
+    NotAFolder { path: String },
     #[error("Failed to read file: {message:?}")]
     FileReadError { message: String },
+    #[error("File `{path:?}` not found")]
+    FileNotFound { path: String },
+    #[error("Failed to write file: {message:?}")]
+    FileWriteError { message: String },
     #[error("Failed to decrypt: {message:?}")]
     DecryptionError { message: String },
     #[error("Note `{note_id:?}` not found")]
     NoteIdNotFound { note_id: String },
+    #[error("`{id:?}` is not a valid note id")]
+    InvalidNoteId { id: String },
+    #[error("Note id `{id:?}` is already used by `{path:?}`")]
+    DuplicateNoteId { id: String, path: String },
     #[error("No note with text `{search_text:?}` found")]
     NoteNotFound { search_text: String },
     #[error("Invalid format: {message:?}")]
@@ -43,12 +81,25 @@ pub enum JoplinReaderError {
     NoEncryptionText,
     #[error("No text found")]
     NoText,
+    #[error("Expected a {expected:?} item, found a {found:?} item")]
+    WrongItemType {
+        expected: JoplinItemType,
+        found: JoplinItemType,
+    },
     #[error("Unexpected end of note")]
     UnexpectedEndOfNote,
+    #[error("Unsupported encryption header version {version}")]
+    UnsupportedHeaderVersion { version: u8 },
     #[error("Unknown encryption method")]
     UnknownEncryptionMethod,
+    #[error("Expected encryption method {expected}, found {found}")]
+    UnexpectedEncryptionMethod { expected: u8, found: u8 },
     #[error("Key id mismatch")]
     KeyIdMismatch,
+    #[error("Note exceeds the size limit of {limit} bytes")]
+    NoteTooLarge { limit: usize },
+    #[error("Note exceeds the limit of {limit} chunks")]
+    TooManyChunks { limit: usize },
 }
 
 #[cfg(test)]
@@ -1,12 +1,19 @@
+use crate::store::{FsStore, NoteStore};
 use crate::JoplinReaderError;
 
+use once_cell::sync::Lazy;
 use regex::{Captures, Regex};
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
-use std::fs;
-use std::io::{BufRead, BufReader};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io::BufRead;
 use std::iter::DoubleEndedIterator;
 use std::path::{Path, PathBuf};
-use std::str::Chars;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::Arc;
 use std::time::SystemTime;
 
 use chrono::NaiveDateTime;
@@ -15,14 +22,229 @@ use sjcl::decrypt_raw;
 use serde;
 use serde::ser::{Serialize, Serializer, SerializeStruct};
 
-/// How often encrypted notes should be refreshed in seconds
+/// How often notes should be refreshed in seconds, if the [`NoteStore`] does
+/// not know when a file was modified
 const REFRESH_INTERVAL: u64 = 60 * 60 * 12;
-/// Size of the full encryption header
-const HEADER_SIZE: u32 = 45;
+/// Size of the header fields before the encryption method: identifier (3),
+/// version (2) and length (6)
+const HEADER_PREFIX_SIZE: usize = 3 + 2 + 6;
+
+/// Default for [`ReadOptions::max_note_bytes`]: 64 MiB
+pub const DEFAULT_MAX_NOTE_BYTES: usize = 64 * 1024 * 1024;
+/// Default for [`ReadOptions::max_note_chunks`]
+pub const DEFAULT_MAX_NOTE_CHUNKS: usize = 100_000;
+
+// Source of the `last_access` stamps of all items, shared between notebooks
+// so stamps only ever grow
+static ACCESS_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Matches the escapes of JavaScript's `escape()`, see [`decode_joplin_escapes`]
+static JOPLIN_ESCAPES: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"%u([dD][89abAB][0-9a-fA-F]{2})%u([dD][c-fC-F][0-9a-fA-F]{2})|%u([0-9a-fA-F]{4})|%([0-9a-fA-F]{2})",
+    )
+    .unwrap()
+});
+
+/// Value produced by a custom property parser
+pub type ParsedProperty = Arc<dyn Any + Send + Sync>;
+/// Custom parser for the raw value of a property
+pub type PropertyParser = Arc<dyn Fn(&str) -> Option<ParsedProperty> + Send + Sync>;
+
+/// Line ending the body of a note is returned with, see
+/// [`ReadOptions::line_ending`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n`, as Joplin stores it
+    #[default]
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+
+/// Options which influence how the content of an item is read and decrypted.
+#[derive(Clone)]
+pub struct ReadOptions {
+    max_note_bytes: usize,
+    max_note_chunks: usize,
+    property_parsers: HashMap<String, PropertyParser>,
+    metadata_only: bool,
+    percent_decode: bool,
+    line_ending: LineEnding,
+    #[cfg(feature = "encoding")]
+    fallback_encoding: Option<&'static encoding_rs::Encoding>,
+}
+
+impl Default for ReadOptions {
+    fn default() -> Self {
+        Self {
+            max_note_bytes: DEFAULT_MAX_NOTE_BYTES,
+            max_note_chunks: DEFAULT_MAX_NOTE_CHUNKS,
+            property_parsers: HashMap::new(),
+            metadata_only: false,
+            percent_decode: true,
+            line_ending: LineEnding::Lf,
+            #[cfg(feature = "encoding")]
+            fallback_encoding: None,
+        }
+    }
+}
+
+impl fmt::Debug for ReadOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ReadOptions");
+        debug
+            .field("max_note_bytes", &self.max_note_bytes)
+            .field("max_note_chunks", &self.max_note_chunks)
+            .field("property_parsers", &self.property_parsers.keys())
+            .field("metadata_only", &self.metadata_only)
+            .field("percent_decode", &self.percent_decode)
+            .field("line_ending", &self.line_ending);
+        #[cfg(feature = "encoding")]
+        debug.field("fallback_encoding", &self.fallback_encoding);
+        debug.finish()
+    }
+}
+
+impl ReadOptions {
+    /// Maximum size of an item in bytes. Reading or decrypting an item which
+    /// is larger fails with [`JoplinReaderError::NoteTooLarge`].
+    pub fn max_note_bytes(mut self, max_note_bytes: usize) -> Self {
+        self.max_note_bytes = max_note_bytes;
+        self
+    }
+
+    pub fn get_max_note_bytes(&self) -> usize {
+        self.max_note_bytes
+    }
+
+    /// Maximum number of chunks the cipher text of an item may consist of.
+    /// Decrypting an item with more fails with
+    /// [`JoplinReaderError::TooManyChunks`] before any chunk is decrypted.
+    pub fn max_note_chunks(mut self, max_note_chunks: usize) -> Self {
+        self.max_note_chunks = max_note_chunks;
+        self
+    }
+
+    pub fn get_max_note_chunks(&self) -> usize {
+        self.max_note_chunks
+    }
+
+    /// Registers a parser for the property `key`. Its result is available
+    /// through [`NoteProperties::parsed_property`], the raw value is kept
+    /// either way. Returning `None` skips the value.
+    pub fn with_property_parser<T, F>(mut self, key: &str, parser: F) -> Self
+    where
+        T: Any + Send + Sync,
+        F: Fn(&str) -> Option<T> + Send + Sync + 'static,
+    {
+        self.property_parsers.insert(
+            key.to_string(),
+            Arc::new(move |value| parser(value).map(|v| Arc::new(v) as ParsedProperty)),
+        );
+        self
+    }
+
+    /// Only read the properties of items, without their body. Encrypted
+    /// items are not decrypted at all, only the properties outside of the
+    /// cipher text (e.g. `id`, `type_`, `updated_time`) are available.
+    /// Reading the body of a note then fails with [`JoplinReaderError::NoText`]
+    /// instead of returning an empty body.
+    pub fn metadata_only(mut self, metadata_only: bool) -> Self {
+        self.metadata_only = metadata_only;
+        self
+    }
+
+    pub fn is_metadata_only(&self) -> bool {
+        self.metadata_only
+    }
+
+    /// Whether the decrypted content of an item is percent-decoded as a
+    /// whole (the default). Every chunk is decrypted and its escapes are
+    /// decoded (see [`decode_joplin_escapes`]) either way, this only
+    /// controls the final decoding after the chunks are joined. Disable it
+    /// for notes in which escapes like `%41` are part of the text.
+    pub fn percent_decode(mut self, percent_decode: bool) -> Self {
+        self.percent_decode = percent_decode;
+        self
+    }
+
+    pub fn is_percent_decoding(&self) -> bool {
+        self.percent_decode
+    }
+
+    /// Line ending of the body of notes. Bodies are read with `\n` by
+    /// default, whatever line endings the file uses.
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    pub fn get_line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Encoding to decode an item with if it is not valid UTF-8, e.g.
+    /// `encoding_rs::WINDOWS_1252` for legacy content. By default only UTF-8
+    /// is accepted.
+    #[cfg(feature = "encoding")]
+    pub fn with_fallback_encoding(mut self, encoding: &'static encoding_rs::Encoding) -> Self {
+        self.fallback_encoding = Some(encoding);
+        self
+    }
+
+    /// Decodes `data` as UTF-8, or with the fallback encoding if set.
+    fn decode(&self, data: Vec<u8>) -> Option<String> {
+        match String::from_utf8(data) {
+            Ok(text) => Some(text),
+            #[cfg(feature = "encoding")]
+            Err(e) => {
+                let encoding = self.fallback_encoding?;
+                encoding
+                    .decode_without_bom_handling_and_without_replacement(e.as_bytes())
+                    .map(|text| text.into_owned())
+            }
+            #[cfg(not(feature = "encoding"))]
+            Err(_) => None,
+        }
+    }
+
+    /// Returns the options which change the content read, to tell whether
+    /// cached content was read with the same ones.
+    fn content_options(&self) -> ContentOptions {
+        let mut property_parsers: Vec<(String, usize)> = self
+            .property_parsers
+            .iter()
+            .map(|(key, parser)| (key.to_string(), Arc::as_ptr(parser) as *const () as usize))
+            .collect();
+        property_parsers.sort_unstable();
+        ContentOptions {
+            metadata_only: self.metadata_only,
+            percent_decode: self.percent_decode,
+            line_ending: self.line_ending,
+            #[cfg(feature = "encoding")]
+            fallback_encoding: self.fallback_encoding,
+            property_parsers,
+        }
+    }
+}
+
+/// The part of [`ReadOptions`] which changes the content read, the limits
+/// only decide whether it can be read at all.
+#[derive(Debug, Clone, PartialEq)]
+struct ContentOptions {
+    metadata_only: bool,
+    percent_decode: bool,
+    line_ending: LineEnding,
+    #[cfg(feature = "encoding")]
+    fallback_encoding: Option<&'static encoding_rs::Encoding>,
+    // Parsers are compared by identity, which cloning the options keeps
+    property_parsers: Vec<(String, usize)>,
+}
 
 /// Various types of items a joplin file can be.
 /// See: https://joplinapp.org/api/references/rest_api/#item-type-ids
-#[derive(Debug, PartialEq, serde::Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
 pub enum JoplinItemType {
     Undefined = 0,
     Note = 1,
@@ -67,6 +289,24 @@ impl From<i32> for JoplinItemType {
     }
 }
 
+/// Markup language the body of a note is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)]
+pub enum MarkupLanguage {
+    Undefined = 0,
+    Markdown = 1,
+    Html = 2,
+}
+
+impl From<i32> for MarkupLanguage {
+    fn from(v: i32) -> Self {
+        match v {
+            1 => MarkupLanguage::Markdown,
+            2 => MarkupLanguage::Html,
+            _ => MarkupLanguage::Undefined,
+        }
+    }
+}
+
 /// Contains general information about a note, and reads a part of the header
 /// when created to check if the note needs to be decrypted (and with which
 /// key).
@@ -75,13 +315,63 @@ pub struct NoteInfo {
     path: PathBuf,
     id: String,
     type_: JoplinItemType,
+    // Whether `type_` is the real type, see `effective_type`
+    type_known: bool,
     encryption_applied: bool,
+    // `encryption_applied` was missing and inferred from the cipher text
+    encryption_inferred: bool,
     parent_id: Option<String>,
     encryption_key_id: Option<String>,
     updated_time: Option<NaiveDateTime>,
+    // Only set for `NoteResource` and `NoteTag` items, which link a note to
+    // a resource or tag
+    note_id: Option<String>,
+    resource_id: Option<String>,
+    tag_id: Option<String>,
+    // Only set for `Alarm` items
+    trigger_time: Option<NaiveDateTime>,
     // `read_time` is when it was read into by **us**
     read_time: Option<SystemTime>,
+    // Modification time of the file when it was read
+    read_mtime: Option<SystemTime>,
+    // Options the content was read with
+    read_options: Option<ContentOptions>,
+    // Stamp of the last read, so the least recently read content can be
+    // dropped first
+    last_access: u64,
     content: NoteProperties,
+    // The content as serialized, without the body which `content` has
+    raw_content: HashMap<String, String>,
+}
+
+/// Items are equal if they have the same id, their content is not compared.
+impl PartialEq for NoteInfo {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for NoteInfo {}
+
+impl Hash for NoteInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+/// Items are ordered by `updated_time` (items without come first), then by
+/// id. Two versions of the same item with different `updated_time`s are
+/// equal but not ordered equal.
+impl Ord for NoteInfo {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.updated_time, &self.id).cmp(&(other.updated_time, &other.id))
+    }
+}
+
+impl PartialOrd for NoteInfo {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl Serialize for NoteInfo {
@@ -89,14 +379,19 @@ impl Serialize for NoteInfo {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("NoteInfo", 9)?;
-        state.serialize_field("created_time", &self.path)?;
+        let mut state = serializer.serialize_struct("NoteInfo", 14)?;
+        state.serialize_field("path", &self.path)?;
+        state.serialize_field("created_time", &self.content.created_time.map(|ct| ct.and_utc().timestamp()))?;
         state.serialize_field("id", &self.id)?;
         state.serialize_field("type_", &self.type_)?;
         state.serialize_field("encryption_applied", &self.encryption_applied)?;
         state.serialize_field("parent_id", &self.parent_id)?;
         state.serialize_field("encryption_key_id", &self.encryption_key_id)?;
-        state.serialize_field("updated_time", &self.updated_time.map_or(0, |ut| ut.timestamp()))?;
+        state.serialize_field("updated_time", &self.updated_time.map_or(0, |ut| ut.and_utc().timestamp()))?;
+        state.serialize_field("note_id", &self.note_id)?;
+        state.serialize_field("resource_id", &self.resource_id)?;
+        state.serialize_field("tag_id", &self.tag_id)?;
+        state.serialize_field("trigger_time", &self.trigger_time.map(|tt| tt.and_utc().timestamp()))?;
         state.serialize_field("read_time", &self.read_time)?;
         state.serialize_field("content", &self.content)?;
         state.end()
@@ -107,7 +402,7 @@ impl Serialize for NoteInfo {
 /// general structure of the note properties from Joplin minus the ones already
 /// read into [`NoteInfo`].
 /// See: https://joplinapp.org/api/references/rest_api/#properties
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct NoteProperties {
     title: Option<String>,
     body: Option<String>,
@@ -118,8 +413,8 @@ pub struct NoteProperties {
     author: Option<String>,
     source_url: Option<String>,
     is_todo: Option<bool>,
-    todo_due: Option<bool>,
-    todo_completed: Option<bool>,
+    todo_due: Option<NaiveDateTime>,
+    todo_completed: Option<NaiveDateTime>,
     source: Option<String>,
     source_application: Option<String>,
     application_data: Option<String>,
@@ -128,34 +423,27 @@ pub struct NoteProperties {
     user_updated_time: Option<NaiveDateTime>,
     markup_language: Option<String>,
     is_shared: Option<bool>,
+    // Only set for resources
+    mime: Option<String>,
+    filename: Option<String>,
+    file_extension: Option<String>,
+    size: Option<u64>,
+    encryption_blob_encrypted: Option<bool>,
+    /// Properties without a field of their own, e.g. added by plugins
+    extra_properties: HashMap<String, String>,
+    /// Results of the custom parsers of [`ReadOptions::with_property_parser`]
+    parsed_properties: HashMap<String, ParsedProperty>,
 }
-impl Default for NoteProperties {
-    fn default() -> Self {
-        Self {
-            title: None,
-            body: None,
-            created_time: None,
-            altitude: None,
-            latitude: None,
-            longitude: None,
-            author: None,
-            source_url: None,
-            is_todo: None,
-            todo_due: None,
-            todo_completed: None,
-            source: None,
-            source_application: None,
-            application_data: None,
-            order: None,
-            user_created_time: None,
-            user_updated_time: None,
-            markup_language: None,
-            is_shared: None,
-        }
+impl From<HashMap<String, String>> for NoteProperties {
+    fn from(kv_store: HashMap<String, String>) -> Self {
+        NoteProperties::from_properties(kv_store, &ReadOptions::default())
     }
 }
-impl From<HashMap<String, String>> for NoteProperties {
-    fn from(mut kv_store: HashMap<String, String>) -> Self {
+
+impl NoteProperties {
+    /// Converts the raw key value pairs of an item, running the custom
+    /// property parsers registered in `options`.
+    pub fn from_properties(mut kv_store: HashMap<String, String>, options: &ReadOptions) -> Self {
         let mut title: Option<String> = None;
         let mut body: Option<String> = None;
         let mut created_time: Option<NaiveDateTime> = None;
@@ -165,8 +453,8 @@ impl From<HashMap<String, String>> for NoteProperties {
         let mut author: Option<String> = None;
         let mut source_url: Option<String> = None;
         let mut is_todo: Option<bool> = None;
-        let mut todo_due: Option<bool> = None;
-        let mut todo_completed: Option<bool> = None;
+        let mut todo_due: Option<NaiveDateTime> = None;
+        let mut todo_completed: Option<NaiveDateTime> = None;
         let mut source: Option<String> = None;
         let mut source_application: Option<String> = None;
         let mut application_data: Option<String> = None;
@@ -175,87 +463,77 @@ impl From<HashMap<String, String>> for NoteProperties {
         let mut user_updated_time: Option<NaiveDateTime> = None;
         let mut markup_language: Option<String> = None;
         let mut is_shared: Option<bool> = None;
+        let mut mime: Option<String> = None;
+        let mut filename: Option<String> = None;
+        let mut file_extension: Option<String> = None;
+        let mut size: Option<u64> = None;
+        let mut encryption_blob_encrypted: Option<bool> = None;
+        let mut extra_properties: HashMap<String, String> = HashMap::new();
+        let mut parsed_properties: HashMap<String, ParsedProperty> = HashMap::new();
 
         for (k, v) in kv_store.drain() {
+            if let Some(parser) = options.property_parsers.get(&k) {
+                if let Some(parsed) = parser(&v) {
+                    parsed_properties.insert(k.clone(), parsed);
+                }
+            }
             match k.as_str() {
                 "title" => title = Some(v),
-                "body" => body = Some(v),
-                "created_time" => {
-                    created_time = match NaiveDateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.fZ")
-                    {
-                        Ok(ut) => Some(ut),
-                        Err(_) => None,
+                "body" => {
+                    body = match options.line_ending {
+                        LineEnding::Lf => Some(v),
+                        LineEnding::CrLf => Some(v.replace("\r\n", "\n").replace('\n', "\r\n")),
                     }
                 }
+                "created_time" => {
+                    created_time = NaiveDateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.fZ").ok()
+                }
                 "altitude" => {
-                    altitude = match v.trim().parse::<f32>() {
-                        Ok(l) => Some(l),
-                        _ => None,
-                    }
+                    altitude = v.trim().parse::<f32>().ok()
                 }
                 "latitude" => {
-                    latitude = match v.trim().parse::<f64>() {
-                        Ok(l) => Some(l),
-                        _ => None,
-                    }
+                    latitude = v.trim().parse::<f64>().ok()
                 }
                 "longitude" => {
-                    longitude = match v.trim().parse::<f64>() {
-                        Ok(l) => Some(l),
-                        _ => None,
-                    }
+                    longitude = v.trim().parse::<f64>().ok()
                 }
                 "author" => author = Some(v),
                 "source_url" => source_url = Some(v),
                 "is_todo" => {
-                    is_todo = match v.trim().parse::<i8>() {
-                        Ok(b) => Some(b == 1),
-                        _ => None,
-                    }
-                }
-                "todo_due" => {
-                    todo_due = match v.trim().parse::<i8>() {
-                        Ok(b) => Some(b == 1),
-                        _ => None,
-                    }
-                }
-                "todo_completed" => {
-                    todo_completed = match v.trim().parse::<i8>() {
-                        Ok(b) => Some(b == 1),
-                        _ => None,
-                    }
+                    is_todo = v.trim().parse::<i8>().ok().map(|b| b == 1)
                 }
+                "todo_due" => todo_due = parse_todo_time(&v),
+                "todo_completed" => todo_completed = parse_todo_time(&v),
                 "source" => source = Some(v),
                 "source_application" => source_application = Some(v),
                 "application_data" => application_data = Some(v),
                 "order" => {
-                    order = match v.trim().parse::<i32>() {
-                        Ok(o) => Some(o),
-                        _ => None,
-                    }
+                    order = v.trim().parse::<i32>().ok()
                 }
                 "user_created_time" => {
                     user_created_time =
-                        match NaiveDateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.fZ") {
-                            Ok(ut) => Some(ut),
-                            Err(_) => None,
-                        }
+                        NaiveDateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.fZ").ok()
                 }
                 "user_updated_time" => {
                     user_updated_time =
-                        match NaiveDateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.fZ") {
-                            Ok(ut) => Some(ut),
-                            Err(_) => None,
-                        }
+                        NaiveDateTime::parse_from_str(&v, "%Y-%m-%dT%H:%M:%S%.fZ").ok()
                 }
                 "markup_language" => markup_language = Some(v),
                 "is_shared" => {
-                    is_shared = match v.trim().parse::<i8>() {
-                        Ok(b) => Some(b == 1),
-                        _ => None,
-                    }
+                    is_shared = v.trim().parse::<i8>().ok().map(|b| b == 1)
+                }
+                "mime" => mime = Some(v),
+                "filename" => filename = Some(v),
+                "file_extension" => file_extension = Some(v),
+                "size" => {
+                    size = v.trim().parse::<u64>().ok()
+                }
+                "encryption_blob_encrypted" => {
+                    encryption_blob_encrypted = v.trim().parse::<i8>().ok().map(|b| b == 1)
+                }
+                _ => {
+                    extra_properties.insert(k, v);
                 }
-                _ => { /* unknown key */ }
             }
         }
 
@@ -279,6 +557,13 @@ impl From<HashMap<String, String>> for NoteProperties {
             user_updated_time,
             markup_language,
             is_shared,
+            mime,
+            filename,
+            file_extension,
+            size,
+            encryption_blob_encrypted,
+            extra_properties,
+            parsed_properties,
         }
     }
 }
@@ -288,44 +573,213 @@ impl Serialize for NoteProperties {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("NoteProperties", 19)?;
-        state.serialize_field("title", &self.title.as_ref().unwrap())?;
-        state.serialize_field("body", &self.body.as_ref().unwrap())?;
-        state.serialize_field("created_time", &self.created_time.as_ref().unwrap().timestamp())?;
-        state.serialize_field("altitude", &self.altitude.as_ref().unwrap())?;
-        state.serialize_field("latitude", &self.latitude.as_ref().unwrap())?;
-        state.serialize_field("longitude", &self.longitude.as_ref().unwrap())?;
-        state.serialize_field("author", &self.author.as_ref().unwrap())?;
-        state.serialize_field("source_url", &self.source_url.as_ref().unwrap())?;
-        state.serialize_field("is_todo", &self.is_todo.as_ref().unwrap())?;
-        state.serialize_field("todo_due", &self.todo_due.as_ref().unwrap())?;
-        state.serialize_field("todo_completed", &self.todo_completed.as_ref().unwrap())?;
-        state.serialize_field("source", &self.source.as_ref().unwrap())?;
-        state.serialize_field("source_application", &self.source_application.as_ref().unwrap())?;
-        state.serialize_field("application_data", &self.application_data.as_ref().unwrap())?;
-        state.serialize_field("order", &self.order.as_ref().unwrap())?;
-        state.serialize_field("user_created_time", &self.user_created_time.as_ref().unwrap().timestamp())?;
-        state.serialize_field("user_updated_time", &self.user_updated_time.as_ref().unwrap().timestamp())?;
-        state.serialize_field("markup_language", &self.markup_language.as_ref().unwrap())?;
-        state.serialize_field("is_shared", &self.is_shared.as_ref().unwrap())?;
+        let mut state = serializer.serialize_struct("NoteProperties", 25)?;
+        state.serialize_field("title", &self.title)?;
+        state.serialize_field("body", &self.body)?;
+        state.serialize_field("created_time", &self.created_time.map(|t| t.and_utc().timestamp()))?;
+        state.serialize_field("altitude", &self.altitude)?;
+        state.serialize_field("latitude", &self.latitude)?;
+        state.serialize_field("longitude", &self.longitude)?;
+        state.serialize_field("author", &self.author)?;
+        state.serialize_field("source_url", &self.source_url)?;
+        state.serialize_field("is_todo", &self.is_todo)?;
+        state.serialize_field("todo_due", &self.todo_due.map(|td| td.and_utc().timestamp()))?;
+        state.serialize_field("todo_completed", &self.todo_completed.map(|tc| tc.and_utc().timestamp()))?;
+        state.serialize_field("source", &self.source)?;
+        state.serialize_field("source_application", &self.source_application)?;
+        state.serialize_field("application_data", &self.application_data)?;
+        state.serialize_field("order", &self.order)?;
+        state.serialize_field("user_created_time", &self.user_created_time.map(|t| t.and_utc().timestamp()))?;
+        state.serialize_field("user_updated_time", &self.user_updated_time.map(|t| t.and_utc().timestamp()))?;
+        state.serialize_field("markup_language", &self.markup_language)?;
+        state.serialize_field("is_shared", &self.is_shared)?;
+        state.serialize_field("mime", &self.mime)?;
+        state.serialize_field("filename", &self.filename)?;
+        state.serialize_field("file_extension", &self.file_extension)?;
+        state.serialize_field("size", &self.size)?;
+        state.serialize_field("encryption_blob_encrypted", &self.encryption_blob_encrypted)?;
+        state.serialize_field("extra_properties", &self.extra_properties)?;
         state.end()
     }
 }
 
+impl NoteProperties {
+    /// Title of the note, or name of a folder or tag
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Content of the note
+    pub fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+
+    /// Where the note was created from, e.g. `joplin-desktop`
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Id of the application that created the note, e.g.
+    /// `net.cozic.joplin-desktop`
+    pub fn source_application(&self) -> Option<&str> {
+        self.source_application.as_deref()
+    }
+
+    /// Url of the page the note was clipped from
+    pub fn source_url(&self) -> Option<&str> {
+        self.source_url.as_deref()
+    }
+
+    /// When the note was created
+    pub fn created_time(&self) -> Option<&NaiveDateTime> {
+        self.created_time.as_ref()
+    }
+
+    /// Whether the note is a to-do
+    pub fn is_todo(&self) -> Option<bool> {
+        self.is_todo
+    }
+
+    /// When the to-do is due, `None` if it has no due date
+    pub fn todo_due(&self) -> Option<&NaiveDateTime> {
+        self.todo_due.as_ref()
+    }
+
+    /// When the to-do was completed, `None` if it is still open
+    pub fn todo_completed(&self) -> Option<&NaiveDateTime> {
+        self.todo_completed.as_ref()
+    }
+
+    /// Latitude of where the note was created
+    pub fn latitude(&self) -> Option<f64> {
+        self.latitude
+    }
+
+    /// Longitude of where the note was created
+    pub fn longitude(&self) -> Option<f64> {
+        self.longitude
+    }
+
+    /// Altitude of where the note was created
+    pub fn altitude(&self) -> Option<f32> {
+        self.altitude
+    }
+
+    /// Mime type of a resource, e.g. `image/png`
+    pub fn mime(&self) -> Option<&str> {
+        self.mime.as_deref()
+    }
+
+    /// Original file name of a resource
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Extension of the file of a resource, without the dot
+    pub fn file_extension(&self) -> Option<&str> {
+        self.file_extension.as_deref()
+    }
+
+    /// Size of the file of a resource in bytes
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// Whether the file of a resource is encrypted (`.crypted`), which is
+    /// independent of the encryption of the resource item itself
+    pub fn encryption_blob_encrypted(&self) -> Option<bool> {
+        self.encryption_blob_encrypted
+    }
+
+    /// When the note was created according to the user, which is what Joplin
+    /// displays. Unlike `created_time` this is kept when an item is synced
+    /// or imported.
+    pub fn user_created_time(&self) -> Option<&NaiveDateTime> {
+        self.user_created_time.as_ref()
+    }
+
+    /// When the note was last changed by the user
+    pub fn user_updated_time(&self) -> Option<&NaiveDateTime> {
+        self.user_updated_time.as_ref()
+    }
+
+    /// Markup language of the body. `None` if the note has no such property
+    /// (e.g. from old Joplin versions), which Joplin treats as Markdown.
+    pub fn markup_language(&self) -> Option<MarkupLanguage> {
+        let markup_language = self.markup_language.as_deref()?;
+        markup_language.trim().parse::<i32>().ok().map(MarkupLanguage::from)
+    }
+
+    /// All properties which are not modelled by a field of their own.
+    pub fn extra_properties(&self) -> &HashMap<String, String> {
+        &self.extra_properties
+    }
+
+    /// Returns the value produced by a custom property parser (see
+    /// [`ReadOptions::with_property_parser`]), if it parsed into a `T`.
+    pub fn parsed_property<T: Any>(&self, key: &str) -> Option<&T> {
+        self.parsed_properties.get(key)?.downcast_ref::<T>()
+    }
+}
+
 /// Leading header of the `encryption_cipher_text` in an item
-#[derive(Debug)]
-struct JoplinEncryptionHeader {
+#[derive(Debug, PartialEq)]
+pub struct JoplinEncryptionHeader {
     version: u8,
     length: u32,
     encryption_method: JoplinEncryptionMethod,
     master_key_id: String,
 }
 
+impl JoplinEncryptionHeader {
+    pub fn get_version(&self) -> u8 {
+        self.version
+    }
+
+    /// Length of the header after the length field (method + master key id)
+    pub fn get_length(&self) -> u32 {
+        self.length
+    }
+
+    pub fn get_encryption_method(&self) -> &JoplinEncryptionMethod {
+        &self.encryption_method
+    }
+
+    pub fn get_master_key_id(&self) -> &str {
+        &self.master_key_id
+    }
+
+    /// Size of the full header, i.e. the offset at which the chunks start
+    pub fn get_size(&self) -> usize {
+        HEADER_PREFIX_SIZE + self.length as usize
+    }
+}
+
+/// A reminder, read from an `Alarm` item.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlarmInfo {
+    /// Id of the note the reminder is for
+    pub note_id: String,
+    /// When the reminder is due
+    pub trigger_time: NaiveDateTime,
+}
+
+/// Statistics collected while decrypting the chunks of an item.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize)]
+pub struct DecryptStats {
+    /// Number of SJCL chunks the cipher text consisted of
+    pub chunks: u32,
+    /// Total length of all chunks as stored in the cipher text
+    pub cipher_bytes: usize,
+    /// Total length of the decrypted chunks, before any decoding is applied
+    pub decrypted_bytes: usize,
+}
+
 /// Joplin defines the various cipher suits and key lengths SJCL provides as
 /// methods in an enumerated fashion.
 /// Method 4 is used for key encryption, and method 1a for notes.
 /// Everything else is deprecated (and also considered unsecure).
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum JoplinEncryptionMethod {
     MethodUndefined = 0x0,
     MethodSjcl = 0x1,
@@ -348,6 +802,19 @@ impl From<u8> for JoplinEncryptionMethod {
     }
 }
 
+impl JoplinEncryptionMethod {
+    /// Whether the method encrypts master keys (2, 3 and 4) rather than
+    /// items (1 and 1a).
+    pub fn is_key_encryption(&self) -> bool {
+        matches!(
+            self,
+            JoplinEncryptionMethod::MethodSjcl2
+                | JoplinEncryptionMethod::MethodSjcl3
+                | JoplinEncryptionMethod::MethodSjcl4
+        )
+    }
+}
+
 impl NoteInfo {
     /// Reads an encrypted file, which has some unencrypted keys as well as the
     /// ciphertext. List of all keys which are stored unencrypted:
@@ -366,21 +833,44 @@ impl NoteInfo {
                 }
             };
 
-            let mut iter = line.splitn(2, ":");
+            let mut iter = line.splitn(2, ':');
             let key = iter.next();
             let value = iter.next();
             if let (Some(key), Some(value)) = (key, value) {
                 // This will update&succeed in case of duplicate keys:
-                kv_store.insert(
-                    key.to_string().trim().to_string(),
-                    value.to_string().trim().to_string(),
-                );
+                kv_store.insert(key.trim().to_string(), value.trim().to_string());
             }
         }
 
         Ok(kv_store)
     }
 
+    /// Whether `line` looks like a serialized property (`key: value`).
+    fn is_property_line(line: &str) -> bool {
+        match line.trim().split_once(':') {
+            Some((key, _)) => {
+                !key.is_empty()
+                    && key
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+            }
+            None => false,
+        }
+    }
+
+    /// Whether the properties are stored before the body instead of after it.
+    fn has_leading_properties(lines: &[String]) -> bool {
+        let mut non_empty = lines.iter().filter(|line| !line.trim().is_empty());
+        let first = non_empty.next();
+        let last = non_empty.next_back().or(first);
+        match (first, last) {
+            (Some(first), Some(last)) => {
+                NoteInfo::is_property_line(first) && !NoteInfo::is_property_line(last)
+            }
+            _ => false,
+        }
+    }
+
     /// So the general format for notes is:
     /// Title\n\nBody\n\n[Prop: PropValue\n,...]
     /// But if they are encrypted, instead some unencrypted properties may be
@@ -388,12 +878,32 @@ impl NoteInfo {
     /// See [`NoteInfo::parse_encrypted_file`].
     /// Serialization:
     /// https://github.com/laurent22/joplin/blob/bfacf71397e21fda5c7c1675365c4199d29de9e7/packages/lib/models/BaseItem.ts#L330
+    /// Items where the properties come first (Props\n\nTitle\n\nBody), as
+    /// written by some older or third-party tools, are also accepted.
+    /// `known_type` is the type already read from the outer file, which is
+    /// used if `text` has no `type_` of its own.
     fn deserialize(
         text: impl DoubleEndedIterator<Item = impl AsRef<str>>,
+        known_type: Option<JoplinItemType>,
     ) -> Result<HashMap<String, String>, JoplinReaderError> {
         let mut kv_store: HashMap<String, String> = HashMap::new();
         let mut body: Vec<String> = Vec::new();
 
+        let mut lines: Vec<String> = text.map(|line| line.as_ref().to_string()).collect();
+        if NoteInfo::has_leading_properties(&lines) {
+            // Move the leading properties to the end
+            let split = lines
+                .iter()
+                .position(|line| line.trim().is_empty())
+                .unwrap_or(lines.len());
+            let mut props: Vec<String> = lines.drain(..split).collect();
+            if !lines.is_empty() {
+                lines.remove(0);
+            }
+            lines.push(String::new());
+            lines.append(&mut props);
+        }
+
         enum ReadingState {
             Props,
             Body,
@@ -401,16 +911,22 @@ impl NoteInfo {
         let mut state: ReadingState = ReadingState::Props;
         // Because \n\n is used for splitting, the content has to be read backwards
         // See: https://github.com/laurent22/joplin/blob/bfacf71397e21fda5c7c1675365c4199d29de9e7/packages/lib/models/BaseItem.ts#L446
-        for line in text.rev() {
-            let line = line.as_ref().trim().to_string();
+        for line in lines.iter().rev() {
             match state {
                 ReadingState::Props => {
+                    if kv_store.is_empty() && !line.trim().is_empty() && !NoteInfo::is_property_line(line) {
+                        // No properties at all, only a title (and body)
+                        state = ReadingState::Body;
+                        body.insert(0, line.to_string());
+                        continue;
+                    }
+                    let line = line.trim();
                     if line.is_empty() {
                         state = ReadingState::Body;
                         continue;
                     }
 
-                    let mut iter = line.splitn(2, ":");
+                    let mut iter = line.splitn(2, ':');
                     let key = iter.next();
                     let value = iter.next();
                     if let (Some(key), Some(value)) = (key, value) {
@@ -426,14 +942,15 @@ impl NoteInfo {
                     }
                 }
                 ReadingState::Body => {
-                    // Since we read backwards, we insert the lines into the beginning
-                    body.insert(0, line);
+                    // Since we read backwards, we insert the lines into the beginning.
+                    // Body lines are kept as they are to preserve indentation.
+                    body.insert(0, line.to_string());
                 }
             }
         }
 
-        let type_ = match kv_store.get(&"type_".to_string()) {
-            Some(t) => match t.parse::<i32>() {
+        let type_ = match (kv_store.get("type_"), known_type) {
+            (Some(t), _) => match t.parse::<i32>() {
                 Ok(t) => JoplinItemType::from(t),
                 Err(_) => {
                     return Err(JoplinReaderError::InvalidFormat {
@@ -441,16 +958,23 @@ impl NoteInfo {
                     });
                 }
             },
-            None => {
+            (None, Some(known_type)) => known_type,
+            // Items without any properties are treated as notes
+            (None, None) if kv_store.is_empty() => JoplinItemType::Note,
+            (None, None) => {
                 return Err(JoplinReaderError::InvalidFormat {
                     message: "Missing required property: `type_`".to_string(),
                 });
             }
         };
 
-        if !body.is_empty() && body.len() >= 2 {
-            kv_store.insert("title".to_string(), body.remove(0));
-            body.remove(0); // Because it is title\n\n
+        // Items without a body (e.g. folders and tags) only have a title
+        if !body.is_empty() {
+            kv_store.insert("title".to_string(), body.remove(0).trim().to_string());
+        }
+        // Because it is title\n\n, but only if the separator is there
+        if body.first().is_some_and(|line| line.trim().is_empty()) {
+            body.remove(0);
         }
         if type_ == JoplinItemType::Note {
             kv_store.insert("body".to_string(), body.join("\n"));
@@ -461,41 +985,46 @@ impl NoteInfo {
 
     /// Reads in a new from a `Path`.
     pub fn new(note_path: &Path) -> Result<NoteInfo, JoplinReaderError> {
-        let file = match fs::File::open(note_path) {
-            Ok(file) => file,
-            Err(_) => {
-                return Err(JoplinReaderError::FileReadError {
-                    message: "Failed to open file".to_string(),
-                })
-            }
-        };
-        let reader = BufReader::new(file);
+        NoteInfo::from_store(&FsStore::default(), &note_path.to_string_lossy())
+    }
+
+    /// Reads in a new from a `path` inside of a [`NoteStore`].
+    pub fn from_store<S: NoteStore>(store: &S, note_path: &str) -> Result<NoteInfo, JoplinReaderError> {
+        let file = store.read_file(note_path)?;
+        // The header is always ascii, the body may be in a legacy encoding
+        let text = String::from_utf8_lossy(&file);
 
         let mut id: Option<String> = None;
         let mut parent_id: Option<String> = None;
         let mut type_: Option<JoplinItemType> = None;
-        let mut encryption_cipher_text: Option<String> = None;
+        // Borrowed from `text`, as the cipher text can be very long
+        let mut encryption_cipher_text: Option<&str> = None;
         let mut encryption_applied: Option<i8> = None;
         let mut updated_time: Option<NaiveDateTime> = None;
+        let mut note_id: Option<String> = None;
+        let mut resource_id: Option<String> = None;
+        let mut tag_id: Option<String> = None;
+        let mut trigger_time: Option<NaiveDateTime> = None;
 
-        for line in reader.lines() {
-            let line = match line {
-                Ok(line) => line,
-                Err(_) => {
-                    return Err(JoplinReaderError::FileReadError {
-                        message: "Failed to read file".to_string(),
-                    })
-                }
-            };
-            let mut iter = line.splitn(2, ":");
+        for line in text.lines() {
+            let mut iter = line.splitn(2, ':');
             let key = iter.next();
             let value = iter.next();
             if let (Some(key), Some(value)) = (key, value) {
+                let value = value.trim();
+                // Empty values (`key:`) are treated as if the key was not set
+                if value.is_empty() {
+                    continue;
+                }
                 match key {
-                    "id" => id = Some(value.to_string().trim().to_string()),
-                    "parent_id" => parent_id = Some(value.to_string().trim().to_string()),
+                    "id" => id = Some(value.to_string()),
+                    "parent_id" => parent_id = Some(value.to_string()),
+                    "note_id" => note_id = Some(value.to_string()),
+                    "resource_id" => resource_id = Some(value.to_string()),
+                    "tag_id" => tag_id = Some(value.to_string()),
+                    "trigger_time" => trigger_time = parse_time(value),
                     "type_" => {
-                        if let Ok(t) = value.to_string().trim().parse::<i32>() {
+                        if let Ok(t) = value.parse::<i32>() {
                             type_ = Some(JoplinItemType::from(t))
                         } else {
                             return Err(JoplinReaderError::FileReadError {
@@ -504,7 +1033,7 @@ impl NoteInfo {
                         }
                     }
                     "encryption_applied" => {
-                        if let Ok(ea) = value.to_string().trim().parse::<i8>() {
+                        if let Ok(ea) = value.parse::<i8>() {
                             encryption_applied = Some(ea)
                         } else {
                             return Err(JoplinReaderError::FileReadError {
@@ -513,16 +1042,10 @@ impl NoteInfo {
                             });
                         }
                     }
-                    "encryption_cipher_text" => {
-                        encryption_cipher_text = Some(value.to_string().trim().to_string())
-                    }
+                    "encryption_cipher_text" => encryption_cipher_text = Some(value),
                     "updated_time" => {
-                        let ut = value.to_string().trim().to_string();
                         updated_time =
-                            match NaiveDateTime::parse_from_str(&ut, "%Y-%m-%dT%H:%M:%S%.fZ") {
-                                Ok(ut) => Some(ut),
-                                Err(_) => None,
-                            }
+                            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.fZ").ok()
                     }
                     _ => { /*println!("Unsupported key: {}", key);*/ }
                 };
@@ -530,45 +1053,76 @@ impl NoteInfo {
         }
 
         // Mandatory attributes:
-        if let None = id {
-            return Err(JoplinReaderError::FileReadError {
-                message: "No `id` specified in note".to_string(),
-            });
-        }
-        if let None = encryption_applied {
-            return Err(JoplinReaderError::FileReadError {
-                message: "No `encryption_applied` attribute specified in note".to_string(),
-            });
-        }
-        let encryption_applied = encryption_applied.unwrap();
+        let id = match id {
+            Some(id) => id,
+            None => {
+                return Err(JoplinReaderError::FileReadError {
+                    message: "No `id` specified in note".to_string(),
+                })
+            }
+        };
+        let type_ = match type_ {
+            Some(type_) => type_,
+            None => {
+                return Err(JoplinReaderError::FileReadError {
+                    message: "No `type_` specified in note".to_string(),
+                })
+            }
+        };
+        // Some items (e.g. from interrupted syncs) lack the flag, but have a
+        // valid cipher text
+        let encryption_inferred = encryption_applied.is_none()
+            && encryption_cipher_text.is_some_and(|text| parse_encrypted_header_lenient(text).is_ok());
         let encryption_applied = match encryption_applied {
-            1 => true,
-            _ => false,
+            Some(encryption_applied) => encryption_applied == 1,
+            None if encryption_inferred => true,
+            None => {
+                return Err(JoplinReaderError::FileReadError {
+                    message: "No `encryption_applied` attribute specified in note".to_string(),
+                })
+            }
         };
-        let encryption_key_id = match encryption_applied {
-            true => match NoteInfo::parse_encrypted_header(
-                encryption_cipher_text.clone().unwrap().chars(),
-            ) {
-                Ok(header) => Some(header.master_key_id),
-                Err(_) => {
-                    return Err(JoplinReaderError::FileReadError {
-                        message: "Failed to read the encryption header".to_string(),
-                    });
+        let encryption_key_id = match (encryption_applied, encryption_cipher_text) {
+            // Items with an unsupported header version are still loaded, so
+            // reading them reports the version
+            (true, Some(encryption_cipher_text)) => {
+                match parse_encrypted_header_lenient(encryption_cipher_text) {
+                    Ok(header) => Some(header.master_key_id),
+                    Err(_) => {
+                        return Err(JoplinReaderError::FileReadError {
+                            message: "Failed to read the encryption header".to_string(),
+                        });
+                    }
                 }
-            },
-            _ => None,
+            }
+            (true, None) => {
+                return Err(JoplinReaderError::FileReadError {
+                    message: "No `encryption_cipher_text` specified in encrypted note".to_string(),
+                })
+            }
+            (false, _) => None,
         };
 
         Ok(NoteInfo {
-            path: note_path.to_path_buf(),
-            id: id.unwrap(),
-            type_: type_.unwrap(),
+            path: PathBuf::from(note_path),
+            id,
+            type_,
+            type_known: !encryption_applied,
             encryption_applied,
+            encryption_inferred,
             parent_id,
             encryption_key_id,
             updated_time,
+            note_id,
+            resource_id,
+            tag_id,
+            trigger_time,
             read_time: None,
+            read_mtime: None,
+            read_options: None,
+            last_access: 0,
             content: NoteProperties::default(),
+            raw_content: HashMap::new(),
         })
     }
 
@@ -576,247 +1130,267 @@ impl NoteInfo {
         &self.id
     }
 
+    /// Path of the file the item is read from, relative to its store.
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+
     pub fn is_encrypted(&self) -> bool {
         self.encryption_applied
     }
 
+    /// Whether the item had no `encryption_applied` attribute and was
+    /// recognized as encrypted by the header of its cipher text.
+    pub fn is_encryption_inferred(&self) -> bool {
+        self.encryption_inferred
+    }
+
     pub fn get_type_(&self) -> &JoplinItemType {
         &self.type_
     }
 
+    /// Returns the type of the item, or `None` while it is not known. The
+    /// properties of encrypted items, their type included, are inside the
+    /// cipher text, so until such an item was read (and decrypted)
+    /// [`NoteInfo::get_type_`] is only the type stored outside of it. Once
+    /// known, the type is kept even if the cached content is dropped.
+    pub fn effective_type(&self) -> Option<JoplinItemType> {
+        if self.type_known {
+            Some(self.type_)
+        } else {
+            None
+        }
+    }
+
+    /// Whether the item is of the type `type_`, or could be because its type
+    /// is not known yet, see [`NoteInfo::effective_type`].
+    pub(crate) fn may_be(&self, type_: JoplinItemType) -> bool {
+        self.effective_type().is_none_or(|t| t == type_)
+    }
+
     pub fn get_parent_id(&self) -> Option<&str> {
         match &self.parent_id {
-            Some(parent_id) => Some(&parent_id),
+            Some(parent_id) => Some(parent_id),
             None => None,
         }
     }
 
-    pub fn get_encryption_key_id(&self) -> Option<&str> {
-        match &self.encryption_key_id {
-            Some(encryption_key_id) => Some(&encryption_key_id),
-            None => None,
-        }
+    /// Last time the item was updated, as stored in its header.
+    pub fn get_updated_time(&self) -> Option<&NaiveDateTime> {
+        self.updated_time.as_ref()
     }
 
-    /// Parses the [`JoplinEncryptionHeader`].
-    /// Spec: https://joplinapp.org/spec/e2ee/
-    fn parse_encrypted_header(
-        mut chars: Chars<'_>,
-    ) -> Result<JoplinEncryptionHeader, JoplinReaderError> {
-        // Header (3 chars): Always 'JED'
-        let mut identifier = String::from("");
-        for _ in 0..3 {
-            if let Some(v) = chars.next() {
-                identifier.push(v);
-            }
-        }
-        if identifier.is_empty() || identifier.len() != 3 {
-            return Err(JoplinReaderError::DecryptionError {
-                message: "Header has invalid size".to_string(),
-            });
-        }
-        if identifier != "JED" {
-            return Err(JoplinReaderError::DecryptionError {
-                message: "Identifier is not 'JED'".to_string(),
-            });
-        }
-        // Version number (2 chars)
-        let mut version = String::from("");
-        for _ in 0..2 {
-            if let Some(v) = chars.next() {
-                version.push(v);
-            }
-        }
-        if version.is_empty() || version.len() != 2 {
-            return Err(JoplinReaderError::DecryptionError {
-                message: "Header has invalid size".to_string(),
-            });
-        }
-        let version = match u8::from_str_radix(&version, 16) {
-            Ok(v) => v,
-            Err(_) => {
-                return Err(JoplinReaderError::DecryptionError {
-                    message: "Version is not a number".to_string(),
-                });
-            }
-        };
-        if version != 1 {
-            return Err(JoplinReaderError::DecryptionError {
-                message: "Invalid version. Needs to be '01'".to_string(),
-            });
-        }
-        // Length (6 chars)
-        let mut length = String::from("");
-        for _ in 0..6 {
-            if let Some(v) = chars.next() {
-                length.push(v);
-            }
-        }
-        if length.is_empty() || length.len() != 6 {
-            return Err(JoplinReaderError::DecryptionError {
-                message: "Header has invalid size".to_string(),
-            });
-        }
-        let length = match u32::from_str_radix(&length, 16) {
-            Ok(v) => v,
-            Err(_) => {
-                return Err(JoplinReaderError::DecryptionError {
-                    message: "Length is not a number".to_string(),
-                });
-            }
-        };
-        if length != 34 {
-            return Err(JoplinReaderError::DecryptionError {
-                message: "Expected length 34: Method + master key id".to_string(),
-            });
-        }
-        // Encryption Method (2 chars)
-        let mut encryption_method = String::from("");
-        for _ in 0..2 {
-            if let Some(v) = chars.next() {
-                encryption_method.push(v);
-            }
-        }
-        if encryption_method.is_empty() || encryption_method.len() != 2 {
-            return Err(JoplinReaderError::DecryptionError {
-                message: "Header has invalid size".to_string(),
-            });
-        }
-        let encryption_method = match u8::from_str_radix(&encryption_method, 16) {
-            Ok(v) => JoplinEncryptionMethod::from(v),
-            Err(_) => {
-                return Err(JoplinReaderError::DecryptionError {
-                    message: "Encryption Method is not a number".to_string(),
-                });
-            }
-        };
-        if encryption_method == JoplinEncryptionMethod::MethodUndefined {
-            return Err(JoplinReaderError::DecryptionError {
-                message: "Unknown decryption method".to_string(),
-            });
-        }
-        // Master key ID (32 chars)
-        let mut master_key_id = String::from("");
-        for _ in 0..32 {
-            if let Some(v) = chars.next() {
-                master_key_id.push(v);
-            }
-        }
-        if master_key_id.is_empty() || master_key_id.len() != 32 {
-            return Err(JoplinReaderError::DecryptionError {
-                message: "Header has invalid size".to_string(),
-            });
-        }
+    /// Id of the linked note, for `NoteResource` and `NoteTag` items.
+    pub fn get_note_id(&self) -> Option<&str> {
+        self.note_id.as_deref()
+    }
 
-        Ok(JoplinEncryptionHeader {
-            version,
-            length,
-            encryption_method,
-            master_key_id,
-        })
+    /// Id of the linked resource, for `NoteResource` items.
+    pub fn get_resource_id(&self) -> Option<&str> {
+        self.resource_id.as_deref()
     }
 
-    fn clean_encoded_ascii(text: String) -> String {
-        let re = Regex::new(r"%([0-9a-fA-F]{2})").unwrap();
+    /// Id of the linked tag, for `NoteTag` items. Encrypted items only have
+    /// it in their content.
+    pub fn get_tag_id(&self) -> Option<&str> {
+        self.tag_id.as_deref()
+    }
 
-        let text = re.replace_all(&text, |caps: &Captures| {
-            let value = caps[0].strip_prefix("%").unwrap();
-            let value = u8::from_str_radix(value, 16).unwrap();
-            let value = value as char;
-            value.to_string()
-        });
+    /// When the reminder is due, for `Alarm` items.
+    pub fn get_trigger_time(&self) -> Option<&NaiveDateTime> {
+        self.trigger_time.as_ref()
+    }
+
+    /// Drops the cached content, so it is read again on the next read.
+    pub fn invalidate(&mut self) {
+        self.read_time = None;
+        self.read_mtime = None;
+        self.read_options = None;
+        self.last_access = 0;
+        self.content = NoteProperties::default();
+        self.raw_content = HashMap::new();
+    }
 
-        text.to_string()
+    /// Keeps the content read so far, but reads it again on the next read,
+    /// e.g. because it was not read with the usual key.
+    pub(crate) fn mark_stale(&mut self) {
+        self.read_time = None;
     }
 
-    fn clean_encoded_unicode(text: String) -> String {
-        let re = Regex::new(r"%u([0-9a-fA-F]{4})").unwrap();
+    /// Whether the content was read and is cached.
+    pub fn is_cached(&self) -> bool {
+        self.read_time.is_some()
+    }
 
-        let text = re.replace_all(&text, |_caps: &Captures| {
-            // We should do this properly, but it's UTF-16 which gets inserted
-            // by my kindle and I do not really need these values.
-            // The text is more important
-            // let value = caps[0].strip_prefix("%u").unwrap();
-            // let value = u32::from_str_radix(value, 16).unwrap();
-            // let value = char::try_from(value).unwrap();
-            "".to_string()
-        });
+    pub(crate) fn get_last_access(&self) -> u64 {
+        self.last_access
+    }
+
+    /// Returns the [`NoteProperties`] read so far. These are only populated
+    /// after the content was read, see [`NoteInfo::read`].
+    pub fn get_content(&self) -> &NoteProperties {
+        &self.content
+    }
 
-        text.to_string()
+    /// Returns the property `key` of the content read so far as it is
+    /// serialized, including the ones [`NoteProperties`] does not model.
+    pub fn get_raw_property(&self, key: &str) -> Option<&str> {
+        match key {
+            "body" => self.content.body(),
+            _ => self.raw_content.get(key).map(String::as_str),
+        }
+    }
+
+    pub fn get_encryption_key_id(&self) -> Option<&str> {
+        match &self.encryption_key_id {
+            Some(encryption_key_id) => Some(encryption_key_id),
+            None => None,
+        }
     }
 
     /// Decrypts all chunks one after another and returns the whole `String`
-    /// or breaks on an error.
-    fn decrypt(mut chars: Chars<'_>, encryption_key: &str) -> Result<String, JoplinReaderError> {
-        let mut _chunks_read: u32 = 0;
-        let mut _bytes_read: u32 = 0;
-        let mut body = String::from("");
-        loop {
-            let mut length = String::from("");
-            for _ in 0..6 {
-                if let Some(v) = chars.next() {
-                    length.push(v);
-                }
-            }
-            if length.is_empty() || length.len() != 6 {
-                break;
-            }
-            let length = match u32::from_str_radix(&length, 16) {
-                Ok(v) => v,
-                Err(_) => {
-                    return Err(JoplinReaderError::DecryptionError {
-                        message: "Length is not a number".to_string(),
-                    });
-                }
-            };
+    /// together with the [`DecryptStats`], or breaks on an error.
+    /// `chunks` is the cipher text without the header.
+    fn decrypt(
+        chunks: &str,
+        encryption_key: &str,
+        options: &ReadOptions,
+    ) -> Result<(String, DecryptStats), JoplinReaderError> {
+        let mut body = String::new();
+        let stats = NoteInfo::decrypt_each_chunk(chunks, encryption_key, options, |data| {
+            body.push_str(data)
+        })?;
+        if !options.percent_decode {
+            return Ok((body, stats));
+        }
+        let body = match percent_decode_str(&body).decode_utf8_lossy() {
+            Cow::Borrowed(_) => body,
+            Cow::Owned(decoded) => decoded,
+        };
+        Ok((body, stats))
+    }
 
-            let mut data = String::from("");
-            for _ in 0..length {
-                if let Some(v) = chars.next() {
-                    data.push(v);
+    /// Decrypts the chunks one after another and passes each decrypted chunk
+    /// to `on_chunk`, or breaks on an error.
+    fn decrypt_each_chunk(
+        chunks: &str,
+        encryption_key: &str,
+        options: &ReadOptions,
+        mut on_chunk: impl FnMut(&str),
+    ) -> Result<DecryptStats, JoplinReaderError> {
+        NoteInfo::decrypt_each_raw_chunk(chunks, encryption_key, options, |data| {
+            match options.decode(data) {
+                Some(data) => {
+                    on_chunk(&decode_escapes(&data));
+                    Ok(())
                 }
+                None => Err(JoplinReaderError::DecryptionError {
+                    message: "Message did not contain valid ascii".to_string(),
+                }),
             }
-            if data.is_empty() || data.len() != length as usize {
-                return Err(JoplinReaderError::UnexpectedEndOfNote);
-            }
-            match decrypt_raw(data, encryption_key.to_string()) {
+        })
+    }
+
+    /// Decrypts the chunks one after another and passes the raw bytes of each
+    /// decrypted chunk to `on_chunk`, or breaks on an error (also if
+    /// `on_chunk` returns one).
+    fn decrypt_each_raw_chunk(
+        chunks: &str,
+        encryption_key: &str,
+        options: &ReadOptions,
+        mut on_chunk: impl FnMut(Vec<u8>) -> Result<(), JoplinReaderError>,
+    ) -> Result<DecryptStats, JoplinReaderError> {
+        let mut stats = DecryptStats::default();
+        let chunks = split_chunks(chunks)?;
+        if chunks.len() > options.max_note_chunks {
+            return Err(JoplinReaderError::TooManyChunks {
+                limit: options.max_note_chunks,
+            });
+        }
+        for data in chunks {
+            match decrypt_raw(data.to_string(), encryption_key.to_string()) {
                 Ok(data) => {
-                    let data = match String::from_utf8(data) {
-                        Ok(data) => data,
-                        Err(_) => {
-                            return Err(JoplinReaderError::DecryptionError {
-                                message: "Message did not contain valid ascii".to_string(),
-                            })
-                        }
-                    };
-                    let data = NoteInfo::clean_encoded_ascii(data);
-                    let data = NoteInfo::clean_encoded_unicode(data);
-                    body.push_str(&data)
+                    stats.decrypted_bytes += data.len();
+                    if stats.decrypted_bytes > options.max_note_bytes {
+                        return Err(JoplinReaderError::NoteTooLarge {
+                            limit: options.max_note_bytes,
+                        });
+                    }
+                    on_chunk(data)?
                 }
-                Err(_) => {
+                Err(e) => {
                     return Err(JoplinReaderError::DecryptionError {
-                        message: "Error decrypting".to_string(),
+                        message: format!("Error decrypting chunk {}: {}", stats.chunks, e),
                     })
                 }
             };
 
-            _bytes_read += length;
-            _chunks_read += 1;
+            stats.cipher_bytes += data.len();
+            stats.chunks += 1;
         }
-        let body = percent_decode_str(&body).decode_utf8_lossy();
-        Ok(body.to_string())
+
+        Ok(stats)
     }
 
-    /// Reads the content into the `content` attribute of `self`
-    fn read_content(&mut self, encryption_key: Option<&str>) -> Result<(), JoplinReaderError> {
-        let content = match self.is_encrypted() {
-            true => self.read_decrypted(encryption_key),
-            false => self.read_unencrypted(),
+    /// Reads (and decrypts) the item and returns all of its properties as
+    /// they are serialized, before they are converted into
+    /// [`NoteProperties`]. Nothing is cached.
+    /// With [`ReadOptions::metadata_only`] the body is left out and encrypted
+    /// items are not decrypted.
+    pub fn read_raw_properties_with<S: NoteStore>(
+        &self,
+        store: &S,
+        encryption_key: Option<&str>,
+        options: &ReadOptions,
+    ) -> Result<HashMap<String, String>, JoplinReaderError> {
+        let mut properties = match (self.is_encrypted(), options.metadata_only) {
+            (true, true) => self.read_metadata(store)?,
+            (true, false) => self.read_decrypted(store, encryption_key, options)?,
+            (false, _) => self.read_unencrypted(store, options)?,
         };
+        if options.metadata_only {
+            properties.remove("body");
+        }
+
+        Ok(properties)
+    }
+
+    /// Reads the properties of an encrypted item which are not part of the
+    /// cipher text.
+    fn read_metadata<S: NoteStore>(&self, store: &S) -> Result<HashMap<String, String>, JoplinReaderError> {
+        let file = store.read_file(&self.path.to_string_lossy())?;
+        let mut properties = NoteInfo::parse_encrypted_file(&mut file.as_slice())?;
+        properties.remove("encryption_cipher_text");
+
+        Ok(properties)
+    }
+
+    /// Reads the content into the `content` attribute of `self`
+    fn read_content<S: NoteStore>(
+        &mut self,
+        store: &S,
+        encryption_key: Option<&str>,
+        options: &ReadOptions,
+    ) -> Result<(), JoplinReaderError> {
+        let content = self.read_raw_properties_with(store, encryption_key, options);
 
         match content {
             Ok(content) => {
-                self.content = NoteProperties::from(content);
+                // The properties of encrypted items are inside the cipher text,
+                // so the outer `type_` is not necessarily the real one.
+                if let Some(t) = content.get("type_").and_then(|t| t.parse::<i32>().ok()) {
+                    self.type_ = JoplinItemType::from(t);
+                }
+                // Without decrypting, only the outer type was read
+                if !(self.encryption_applied && options.metadata_only) {
+                    self.type_known = true;
+                }
+                self.raw_content = content
+                    .iter()
+                    .filter(|(key, _)| *key != "body")
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .collect();
+                self.content = NoteProperties::from_properties(content, options);
                 Ok(())
             }
             Err(e) => Err(e),
@@ -825,105 +1399,867 @@ impl NoteInfo {
 
     /// Read an unencrypted item and return a [`std::collection::HashMap`]
     /// with the key value pairs
-    fn read_unencrypted(&self) -> Result<HashMap<String, String>, JoplinReaderError> {
-        let file = match fs::File::open(self.path.clone()) {
-            Ok(file) => file,
-            Err(_) => {
+    fn read_unencrypted<S: NoteStore>(
+        &self,
+        store: &S,
+        options: &ReadOptions,
+    ) -> Result<HashMap<String, String>, JoplinReaderError> {
+        let file = store.read_file(&self.path.to_string_lossy())?;
+        if file.len() > options.max_note_bytes {
+            return Err(JoplinReaderError::NoteTooLarge {
+                limit: options.max_note_bytes,
+            });
+        }
+        let text = match options.decode(file) {
+            Some(text) => text,
+            None => {
                 return Err(JoplinReaderError::FileReadError {
-                    message: "Failed to open file".to_string(),
+                    message: "Failed to read file".to_string(),
                 })
             }
         };
-        let reader = BufReader::new(file);
-        let mut text: Vec<String> = Vec::new();
-        // Reverse the order of the lines
-        for line in reader.lines() {
-            let line = line.unwrap();
-            text.insert(0, line);
-        }
 
-        NoteInfo::deserialize(text.iter())
+        NoteInfo::deserialize(text.lines(), Some(self.type_))
     }
 
     /// Read and decrypt an encrypted item and return a
     /// [`std::collection::HashMap`] with the key value pairs
-    fn read_decrypted(
+    fn read_decrypted<S: NoteStore>(
         &self,
+        store: &S,
         encryption_key: Option<&str>,
+        options: &ReadOptions,
     ) -> Result<HashMap<String, String>, JoplinReaderError> {
+        let (plaintext, _) = self.decrypt_from(store, encryption_key, options)?;
+
+        NoteInfo::deserialize(plaintext.lines(), Some(self.type_))
+    }
+
+    /// Reads the `encryption_cipher_text` of an encrypted item and decrypts
+    /// it into the plaintext serialization of the item.
+    fn decrypt_from<S: NoteStore>(
+        &self,
+        store: &S,
+        encryption_key: Option<&str>,
+        options: &ReadOptions,
+    ) -> Result<(String, DecryptStats), JoplinReaderError> {
         let encryption_key = match encryption_key {
             Some(ek) => ek,
             _ => {
                 return Err(JoplinReaderError::NoEncryptionKey { key: format!("{:?}", encryption_key)});
             }
         };
+        let text = self.read_cipher_text(store)?;
+        let chunks = skip_encrypted_header(&text)?;
+        NoteInfo::decrypt(chunks, encryption_key, options)
+    }
 
-        let file = match fs::File::open(&self.path) {
-            Ok(file) => file,
+    /// Reads the `encryption_cipher_text` of an encrypted item.
+    fn read_cipher_text<S: NoteStore>(&self, store: &S) -> Result<String, JoplinReaderError> {
+        let file = store.read_file(&self.path.to_string_lossy())?;
+        let mut text = match String::from_utf8(file) {
+            Ok(text) => text,
             Err(_) => {
                 return Err(JoplinReaderError::FileReadError {
-                    message: "Failed to open file".to_string(),
+                    message: "Failed to read file".to_string(),
                 })
             }
         };
-        let mut reader = BufReader::new(file);
-        let content = match NoteInfo::parse_encrypted_file(&mut reader) {
-            Ok(content) => content,
-            Err(e) => return Err(e),
-        };
 
-        if let Some(text) = content.get(&"encryption_cipher_text".to_string()) {
-            if !text.is_ascii() {
+        // The cipher text is cut out of the file in place instead of being
+        // copied, as it can be very long. The last one wins, like for other
+        // duplicate properties.
+        let cipher_text = text
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .filter(|(key, _)| key.trim() == "encryption_cipher_text")
+            .map(|(_, value)| value.trim())
+            .next_back();
+        let (start, end) = match cipher_text {
+            Some(cipher_text) if !cipher_text.is_ascii() => {
                 return Err(JoplinReaderError::DecryptionError {
                     message: "Encrypted text is not ascii".to_string(),
+                })
+            }
+            Some(cipher_text) => {
+                let start = cipher_text.as_ptr() as usize - text.as_ptr() as usize;
+                (start, start + cipher_text.len())
+            }
+            None => return Err(JoplinReaderError::NoEncryptionText),
+        };
+        text.truncate(end);
+        text.replace_range(..start, "");
+
+        Ok(text)
+    }
+
+    /// Returns the serialized item as raw bytes: the decrypted chunks joined
+    /// without any text decoding for encrypted items, or the file content
+    /// for unencrypted ones.
+    pub fn read_bytes_with<S: NoteStore>(
+        &self,
+        store: &S,
+        encryption_key: Option<&str>,
+        options: &ReadOptions,
+    ) -> Result<Vec<u8>, JoplinReaderError> {
+        if !self.is_encrypted() {
+            let file = store.read_file(&self.path.to_string_lossy())?;
+            if file.len() > options.max_note_bytes {
+                return Err(JoplinReaderError::NoteTooLarge {
+                    limit: options.max_note_bytes,
                 });
             }
-            let mut chars = text.chars();
-            // Skip header
-            for _ in 0..HEADER_SIZE {
-                chars.next();
+            return Ok(file);
+        }
+        let encryption_key = match encryption_key {
+            Some(ek) => ek,
+            _ => {
+                return Err(JoplinReaderError::NoEncryptionKey { key: format!("{:?}", encryption_key)});
             }
-            let plaintext = match NoteInfo::decrypt(chars, encryption_key) {
-                Ok(plaintext) => plaintext,
-                Err(_e) => {
-                    println!("{:?}", _e);
-                    return Err(JoplinReaderError::DecryptionError {
-                        message: "Failed to decrypt SJCL chunks".to_string(),
-                    });
-                }
-            };
+        };
+        let text = self.read_cipher_text(store)?;
+        let chunks = skip_encrypted_header(&text)?;
+        let mut bytes: Vec<u8> = Vec::new();
+        NoteInfo::decrypt_each_raw_chunk(chunks, encryption_key, options, |mut data| {
+            bytes.append(&mut data);
+            Ok(())
+        })?;
 
-            NoteInfo::deserialize(plaintext.lines())
-        } else {
-            Err(JoplinReaderError::NoEncryptionText)
+        Ok(bytes)
+    }
+
+    /// Decrypts the item and returns how many chunks and bytes it consisted
+    /// of. Unencrypted items always return empty [`DecryptStats`].
+    /// The content is decrypted anew, the cached content is not touched.
+    pub fn decrypt_stats_from<S: NoteStore>(
+        &self,
+        store: &S,
+        encryption_key: Option<&str>,
+        options: &ReadOptions,
+    ) -> Result<DecryptStats, JoplinReaderError> {
+        if !self.is_encrypted() {
+            return Ok(DecryptStats::default());
         }
+        let (_, stats) = self.decrypt_from(store, encryption_key, options)?;
+
+        Ok(stats)
     }
 
-    /// The content is only read when not existant or after a certain amount of
-    /// time has passed. That is written into the attributes of `self` and
+    /// The content is only read when not existant or when the file was modified
+    /// since (after a certain amount of time has passed, if the modification
+    /// time is unknown). That is written into the attributes of `self` and
     /// returned directly from the body.
     pub fn read(&mut self, encryption_key: Option<&str>) -> Result<&str, JoplinReaderError> {
-        let reading = match self.read_time {
-            None => self.read_content(encryption_key),
-            Some(t) => {
-                let since_last_refresh = SystemTime::now()
-                    .duration_since(t)
-                    .expect("Time went backwards!")
-                    .as_secs();
-                if since_last_refresh >= REFRESH_INTERVAL {
-                    self.read_content(encryption_key)
-                } else {
-                    Ok(())
-                }
+        self.read_from(&FsStore::default(), encryption_key)
+    }
+
+    /// Same as [`NoteInfo::read`], but reads the item from a [`NoteStore`].
+    pub fn read_from<S: NoteStore>(
+        &mut self,
+        store: &S,
+        encryption_key: Option<&str>,
+    ) -> Result<&str, JoplinReaderError> {
+        self.read_with(store, encryption_key, &ReadOptions::default())
+    }
+
+    /// Same as [`NoteInfo::read_from`], but with custom [`ReadOptions`].
+    /// Notes with only a title return an empty body, items which have no body
+    /// by design (e.g. folders) and notes read with
+    /// [`ReadOptions::metadata_only`] fail with [`JoplinReaderError::NoText`].
+    pub fn read_with<S: NoteStore>(
+        &mut self,
+        store: &S,
+        encryption_key: Option<&str>,
+        options: &ReadOptions,
+    ) -> Result<&str, JoplinReaderError> {
+        self.read_properties_with(store, encryption_key, options)?;
+        self.cached_body()
+    }
+
+    /// Returns the body of the content read so far, like
+    /// [`NoteInfo::read_with`] does.
+    pub(crate) fn cached_body(&self) -> Result<&str, JoplinReaderError> {
+        match &self.content.body {
+            Some(body) => Ok(body),
+            // The body was not read at all
+            None if self.read_options.as_ref().is_some_and(|options| options.metadata_only) => {
+                Err(JoplinReaderError::NoText)
             }
+            // A note without body lines just has an empty body
+            None if self.type_ == JoplinItemType::Note => Ok(""),
+            // Other items (e.g. folders) have no body at all
+            None => Err(JoplinReaderError::NoText),
+        }
+    }
+
+    /// Same as [`NoteInfo::read_from`], but returns all [`NoteProperties`]
+    /// instead of only the body.
+    pub fn read_properties_from<S: NoteStore>(
+        &mut self,
+        store: &S,
+        encryption_key: Option<&str>,
+    ) -> Result<&NoteProperties, JoplinReaderError> {
+        self.read_properties_with(store, encryption_key, &ReadOptions::default())
+    }
+
+    /// Same as [`NoteInfo::read_properties_from`], but with custom
+    /// [`ReadOptions`].
+    pub fn read_properties_with<S: NoteStore>(
+        &mut self,
+        store: &S,
+        encryption_key: Option<&str>,
+        options: &ReadOptions,
+    ) -> Result<&NoteProperties, JoplinReaderError> {
+        // Re-read when the file changed, or after `REFRESH_INTERVAL` if the
+        // store has no modification times, and when reading with other options
+        let modified = store.modified(&self.path.to_string_lossy());
+        let content_options = options.content_options();
+        let needs_refresh = match (self.read_time, modified) {
+            (None, _) => true,
+            _ if self.read_options.as_ref() != Some(&content_options) => true,
+            (Some(_), Some(modified)) => self.read_mtime != Some(modified),
+            (Some(t), None) => match SystemTime::now().duration_since(t) {
+                Ok(since_last_refresh) => since_last_refresh.as_secs() >= REFRESH_INTERVAL,
+                Err(_) => true,
+            },
+        };
+        let reading = if needs_refresh {
+            self.read_content(store, encryption_key, options).map(|_| {
+                self.read_time = Some(SystemTime::now());
+                self.read_mtime = modified;
+                self.read_options = Some(content_options);
+            })
+        } else {
+            Ok(())
         };
 
         match reading {
-            Ok(_) => match &self.content.body {
-                Some(body) => Ok(body),
-                None => Err(JoplinReaderError::NoText),
-            },
+            Ok(_) => {
+                self.last_access = ACCESS_COUNTER.fetch_add(1, AtomicOrdering::Relaxed) + 1;
+                Ok(&self.content)
+            }
             Err(e) => Err(e),
         }
     }
 }
+
+/// Parses a timestamp, either in the ISO format Joplin uses for serialized
+/// items or as milliseconds since the epoch.
+pub(crate) fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    match NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.fZ") {
+        Ok(time) => Some(time),
+        Err(_) => {
+            let millis = value.parse::<i64>().ok()?;
+            chrono::DateTime::from_timestamp_millis(millis).map(|time| time.naive_utc())
+        }
+    }
+}
+
+/// Parses `todo_due` and `todo_completed`, which Joplin stores as
+/// milliseconds since the epoch, where `0` means not set.
+fn parse_todo_time(value: &str) -> Option<NaiveDateTime> {
+    match value.trim() {
+        "0" => None,
+        value => parse_time(value),
+    }
+}
+
+/// Returns `length` characters of the header starting at `offset`.
+fn header_field(cipher_text: &str, offset: usize, length: usize) -> Result<&str, JoplinReaderError> {
+    match cipher_text.get(offset..offset + length) {
+        Some(field) => Ok(field),
+        None => Err(JoplinReaderError::DecryptionError {
+            message: "Header has invalid size".to_string(),
+        }),
+    }
+}
+
+/// Parses a hexadecimal number, `name` is used in the error message.
+fn parse_hex(text: &str, name: &str) -> Result<u32, JoplinReaderError> {
+    if !text.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(JoplinReaderError::DecryptionError {
+            message: format!("{} is not a number", name),
+        });
+    }
+    match u32::from_str_radix(text, 16) {
+        Ok(v) => Ok(v),
+        Err(_) => Err(JoplinReaderError::DecryptionError {
+            message: format!("{} is not a number", name),
+        }),
+    }
+}
+
+/// Parses the [`JoplinEncryptionHeader`] at the start of an
+/// `encryption_cipher_text`. Headers of other versions than `01` fail with
+/// [`JoplinReaderError::UnsupportedHeaderVersion`], see
+/// [`parse_encrypted_header_lenient`] to read them anyway.
+/// Spec: https://joplinapp.org/spec/e2ee/
+pub fn parse_encrypted_header(cipher_text: &str) -> Result<JoplinEncryptionHeader, JoplinReaderError> {
+    let version = parse_header_version(cipher_text)?;
+    if version != 1 {
+        return Err(JoplinReaderError::UnsupportedHeaderVersion { version });
+    }
+    let header = parse_encrypted_header_lenient(cipher_text)?;
+    if header.length != 34 {
+        return Err(JoplinReaderError::DecryptionError {
+            message: "Expected length 34: Method + master key id".to_string(),
+        });
+    }
+
+    Ok(header)
+}
+
+/// Parses the [`JoplinEncryptionHeader`] of any version, assuming later
+/// versions only append fields to the layout of version `01`. Useful to
+/// report the version and master key of items written by a newer Joplin,
+/// whose cipher text cannot necessarily be decrypted.
+pub fn parse_encrypted_header_lenient(cipher_text: &str) -> Result<JoplinEncryptionHeader, JoplinReaderError> {
+    let version = parse_header_version(cipher_text)?;
+    // Length (6 chars)
+    let length = parse_hex(header_field(cipher_text, 5, 6)?, "Length")?;
+    if length < 34 {
+        return Err(JoplinReaderError::DecryptionError {
+            message: "Expected at least length 34: Method + master key id".to_string(),
+        });
+    }
+    // Encryption Method (2 chars)
+    let encryption_method = parse_hex(header_field(cipher_text, HEADER_PREFIX_SIZE, 2)?, "Encryption Method")?;
+    let encryption_method = JoplinEncryptionMethod::from(encryption_method as u8);
+    if encryption_method == JoplinEncryptionMethod::MethodUndefined {
+        return Err(JoplinReaderError::DecryptionError {
+            message: "Unknown decryption method".to_string(),
+        });
+    }
+    // Master key ID (32 chars)
+    let master_key_id = header_field(cipher_text, HEADER_PREFIX_SIZE + 2, 32)?.to_string();
+
+    Ok(JoplinEncryptionHeader {
+        version,
+        length,
+        encryption_method,
+        master_key_id,
+    })
+}
+
+/// Returns the offset at which the chunks of an `encryption_cipher_text`
+/// start, i.e. the size of its header. It is taken from the length field of
+/// the header, so it also works for versions other than `01` (see
+/// [`parse_encrypted_header_lenient`]). The header is ascii, so this is both
+/// a character and a byte offset.
+pub fn cipher_body_offset(cipher_text: &str) -> Result<usize, JoplinReaderError> {
+    Ok(parse_encrypted_header_lenient(cipher_text)?.get_size())
+}
+
+/// Checks the identifier of a header and returns its version.
+fn parse_header_version(cipher_text: &str) -> Result<u8, JoplinReaderError> {
+    // Header (3 chars): Always 'JED'
+    if header_field(cipher_text, 0, 3)? != "JED" {
+        return Err(JoplinReaderError::DecryptionError {
+            message: "Identifier is not 'JED'".to_string(),
+        });
+    }
+    // Version number (2 chars)
+    Ok(parse_hex(header_field(cipher_text, 3, 2)?, "Version")? as u8)
+}
+
+/// Returns the chunked part of a cipher text, after the header. Fails for
+/// key encryption methods, as their data is not chunked.
+fn skip_encrypted_header(cipher_text: &str) -> Result<&str, JoplinReaderError> {
+    let header = parse_encrypted_header(cipher_text)?;
+    let method = *header.get_encryption_method();
+    if method.is_key_encryption() {
+        return Err(JoplinReaderError::UnexpectedEncryptionMethod {
+            expected: JoplinEncryptionMethod::MethodSjcl1a as u8,
+            found: method as u8,
+        });
+    }
+    match cipher_text.get(header.get_size()..) {
+        Some(chunks) => Ok(chunks),
+        None => Err(JoplinReaderError::DecryptionError {
+            message: "Header has invalid size".to_string(),
+        }),
+    }
+}
+
+/// Decodes the escapes in a decrypted chunk, as produced by JavaScript's
+/// `escape()`: `%XX` is a Latin-1 character and `%uXXXX` a UTF-16 code unit,
+/// where surrogate pairs are combined into one character. Lone surrogates
+/// become `U+FFFD`, everything else is kept as is.
+pub fn decode_joplin_escapes(text: &str) -> String {
+    decode_escapes(text).into_owned()
+}
+
+/// Like [`decode_joplin_escapes`], but only allocates if `text` contains
+/// escapes.
+fn decode_escapes(text: &str) -> Cow<'_, str> {
+    let hex = |value: &str| u32::from_str_radix(value, 16).unwrap();
+
+    JOPLIN_ESCAPES.replace_all(text, |caps: &Captures| {
+        let value = match (caps.get(1), caps.get(2), caps.get(3), caps.get(4)) {
+            (Some(high), Some(low), _, _) => {
+                let value = 0x10000 + ((hex(high.as_str()) - 0xD800) << 10) + (hex(low.as_str()) - 0xDC00);
+                char::from_u32(value)
+            }
+            (_, _, Some(unit), _) => char::from_u32(hex(unit.as_str())),
+            (_, _, _, Some(byte)) => char::from_u32(hex(byte.as_str())),
+            _ => None,
+        };
+        value.unwrap_or(char::REPLACEMENT_CHARACTER).to_string()
+    })
+}
+
+/// Splits the chunked part of a cipher text (everything after the header)
+/// into the raw SJCL chunks. Every chunk is prefixed by its length as 6
+/// hexadecimal characters. Trailing data too short for a length is ignored.
+pub fn split_chunks(text: &str) -> Result<Vec<&str>, JoplinReaderError> {
+    let mut chunks: Vec<&str> = Vec::new();
+    let mut offset: usize = 0;
+    while let Some(length) = text.get(offset..offset + 6) {
+        let length = parse_hex(length, "Length")? as usize;
+        offset += 6;
+
+        let data = match text.get(offset..offset + length) {
+            Some(data) if !data.is_empty() => data,
+            _ => return Err(JoplinReaderError::UnexpectedEndOfNote),
+        };
+        chunks.push(data);
+        offset += length;
+    }
+
+    Ok(chunks)
+}
+
+/// Returns the raw SJCL JSON of every chunk of an `encryption_cipher_text`
+/// (including its header), without decrypting them.
+pub fn raw_chunks(cipher_text: &str) -> Result<Vec<String>, JoplinReaderError> {
+    let text = skip_encrypted_header(cipher_text)?;
+    Ok(split_chunks(text)?.into_iter().map(|chunk| chunk.to_string()).collect())
+}
+
+/// Decrypts an `encryption_cipher_text` (including its header) and returns
+/// every chunk separately instead of the joined body. The escapes are
+/// decoded per chunk (see [`decode_joplin_escapes`]), but the final percent-decoding of the body
+/// is not applied, as escapes may be split across chunks.
+pub fn decrypt_chunks(cipher_text: &str, key: &str) -> Result<Vec<String>, JoplinReaderError> {
+    decrypt_chunks_with(cipher_text, key, &ReadOptions::default())
+}
+
+/// Same as [`decrypt_chunks`], but with custom [`ReadOptions`], e.g. to
+/// raise [`ReadOptions::max_note_bytes`] for large resources.
+pub fn decrypt_chunks_with(
+    cipher_text: &str,
+    key: &str,
+    options: &ReadOptions,
+) -> Result<Vec<String>, JoplinReaderError> {
+    if !cipher_text.is_ascii() {
+        return Err(JoplinReaderError::DecryptionError {
+            message: "Encrypted text is not ascii".to_string(),
+        });
+    }
+    let text = skip_encrypted_header(cipher_text)?;
+
+    let mut chunks: Vec<String> = Vec::new();
+    NoteInfo::decrypt_each_chunk(text, key, options, |data| {
+        chunks.push(data.to_string())
+    })?;
+
+    Ok(chunks)
+}
+
+/// Parses the plaintext serialization of an item (e.g. decrypted elsewhere)
+/// into its [`NoteProperties`], the same way items are read from files.
+/// Fails if `text` has properties but no `type_`.
+pub fn parse_decrypted_note(text: &str) -> Result<NoteProperties, JoplinReaderError> {
+    let properties = NoteInfo::deserialize(text.lines(), None)?;
+
+    Ok(NoteProperties::from_properties(properties, &ReadOptions::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn parse_encrypted_header_never_panics(text in "\\PC*") {
+            let _ = parse_encrypted_header(&text);
+        }
+
+        #[test]
+        fn parse_encrypted_header_never_panics_after_identifier(text in "JED[0-9a-fA-F]{0,16}\\PC{0,40}") {
+            let _ = parse_encrypted_header(&text);
+        }
+
+        #[test]
+        fn parse_encrypted_header_reads_valid_headers(
+            method in 1u8..=5,
+            master_key_id in "[0-9a-f]{32}",
+            rest in "\\PC*",
+        ) {
+            let text = format!("JED01000022{:02x}{}{}", method, master_key_id, rest);
+            let header = parse_encrypted_header(&text).unwrap();
+            prop_assert_eq!(header.get_version(), 1);
+            prop_assert_eq!(header.get_encryption_method(), &JoplinEncryptionMethod::from(method));
+            prop_assert_eq!(header.get_master_key_id(), master_key_id.as_str());
+        }
+
+        #[test]
+        fn split_chunks_never_panics(text in "([0-9a-fA-F]{6}\\PC{0,24}){0,4}") {
+            let _ = split_chunks(&text);
+        }
+
+        #[test]
+        fn split_chunks_returns_all_chunks(chunks in prop::collection::vec("[ -~]{1,300}", 0..5)) {
+            let text: String = chunks.iter().map(|c| format!("{:06x}{}", c.len(), c)).collect();
+            prop_assert_eq!(split_chunks(&text).unwrap(), chunks);
+        }
+    }
+
+    fn note_from_text(text: &str) -> Result<NoteInfo, JoplinReaderError> {
+        let mut store = MemoryStore::new();
+        store.insert("note.md", text);
+        NoteInfo::from_store(&store, "note.md")
+    }
+
+    #[test]
+    fn deserialize_accepts_leading_properties() {
+        let text = "id: 9a20a9e4d336de70cb6d22a58a3e673c\ntype_: 1\n\nTitle\n\nFirst line\nSecond line";
+        let kv_store = NoteInfo::deserialize(text.lines(), None).unwrap();
+
+        assert_eq!(kv_store.get("id").unwrap(), "9a20a9e4d336de70cb6d22a58a3e673c");
+        assert_eq!(kv_store.get("title").unwrap(), "Title");
+        assert_eq!(kv_store.get("body").unwrap(), "First line\nSecond line");
+    }
+
+    #[test]
+    fn new_rejects_empty_id() {
+        let note = note_from_text("Title\n\nBody\n\nid: \nencryption_applied: 0\ntype_: 1");
+        match note {
+            Err(JoplinReaderError::FileReadError { message }) => {
+                assert_eq!(message, "No `id` specified in note")
+            }
+            _ => panic!("Expected an error for an empty `id`"),
+        }
+    }
+
+    #[test]
+    fn new_rejects_empty_cipher_text() {
+        let note = note_from_text(
+            "id: 9a20a9e4d336de70cb6d22a58a3e673c\nencryption_cipher_text:\nencryption_applied: 1\ntype_: 1",
+        );
+        match note {
+            Err(JoplinReaderError::FileReadError { message }) => assert_eq!(
+                message,
+                "No `encryption_cipher_text` specified in encrypted note"
+            ),
+            _ => panic!("Expected an error for an empty `encryption_cipher_text`"),
+        }
+    }
+
+    #[test]
+    fn deserialize_preserves_body_indentation() {
+        let text = "  Title  \n\nfn main() {\n    println!(\"hi\");\n}  \n\nid: 9a20a9e4d336de70cb6d22a58a3e673c\ntype_: 1";
+        let kv_store = NoteInfo::deserialize(text.lines(), None).unwrap();
+
+        assert_eq!(kv_store.get("title").unwrap(), "Title");
+        assert_eq!(
+            kv_store.get("body").unwrap(),
+            "fn main() {\n    println!(\"hi\");\n}  "
+        );
+    }
+
+    #[test]
+    fn custom_property_parsers_and_unknown_keys() {
+        let options = ReadOptions::default()
+            .with_property_parser("plugin_rating", |v| v.parse::<u8>().ok());
+        let mut kv_store = HashMap::new();
+        kv_store.insert("plugin_rating".to_string(), "4".to_string());
+        kv_store.insert("title".to_string(), "Title".to_string());
+        let properties = NoteProperties::from_properties(kv_store, &options);
+        assert_eq!(properties.parsed_property::<u8>("plugin_rating"), Some(&4));
+        assert_eq!(properties.parsed_property::<u32>("plugin_rating"), None);
+        assert_eq!(
+            properties.extra_properties().get("plugin_rating").map(String::as_str),
+            Some("4")
+        );
+        assert!(!properties.extra_properties().contains_key("title"));
+    }
+
+    #[test]
+    fn bodies_can_use_crlf_line_endings() {
+        let mut store = MemoryStore::new();
+        store.insert(
+            "note.md",
+            "Title\r\n\r\nFirst\r\nSecond\n\nid: 9a20a9e4d336de70cb6d22a58a3e673c\ntype_: 1\nencryption_applied: 0",
+        );
+        let mut note = NoteInfo::from_store(&store, "note.md").unwrap();
+        assert_eq!(note.read_from(&store, None).unwrap(), "First\nSecond");
+
+        // The cached content is read again with other options
+        let options = ReadOptions::default().line_ending(LineEnding::CrLf);
+        assert_eq!(note.read_with(&store, None, &options).unwrap(), "First\r\nSecond");
+    }
+
+    #[test]
+    fn user_times_are_exposed() {
+        let mut kv_store = HashMap::new();
+        kv_store.insert("user_created_time".to_string(), "2020-01-01T08:00:00.000Z".to_string());
+        kv_store.insert("user_updated_time".to_string(), "2021-06-15T12:30:00.000Z".to_string());
+        let properties = NoteProperties::from(kv_store);
+        let time = |t: &str| NaiveDateTime::parse_from_str(t, "%Y-%m-%dT%H:%M:%S").unwrap();
+        assert_eq!(properties.user_created_time(), Some(&time("2020-01-01T08:00:00")));
+        assert_eq!(properties.user_updated_time(), Some(&time("2021-06-15T12:30:00")));
+    }
+
+    #[test]
+    fn notes_compare_by_id_and_sort_by_updated_time() {
+        let mut store = MemoryStore::new();
+        let item = |id: &str, updated_time: &str| {
+            format!("Title\n\nid: {}\nupdated_time: {}\ntype_: 1\nencryption_applied: 0", id, updated_time)
+        };
+        store.insert("a.md", item("a", "2021-01-01T00:00:00.000Z"));
+        store.insert("b.md", item("b", "2020-01-01T00:00:00.000Z"));
+        store.insert("a2.md", item("a", "2022-01-01T00:00:00.000Z"));
+        let a = NoteInfo::from_store(&store, "a.md").unwrap();
+        let b = NoteInfo::from_store(&store, "b.md").unwrap();
+        let a2 = NoteInfo::from_store(&store, "a2.md").unwrap();
+
+        assert_eq!(a, a2);
+        assert_ne!(a, b);
+        let mut notes = [&a2, &a, &b];
+        notes.sort();
+        let ids: Vec<&str> = notes.iter().map(|n| n.get_id()).collect();
+        assert_eq!(ids, vec!["b", "a", "a"]);
+        assert!(notes[1].get_updated_time() < notes[2].get_updated_time());
+    }
+
+    #[test]
+    fn decrypt_chunks_starts_after_header() {
+        let cipher_text = r#"JED01000022053336eb7a2472d9ae4a690a978fa8a46f0000a5{"iv":"YjTWP05NwoStCiBgiffE9Q==","v":1,"iter":1000,"ks":128,"ts":64,"mode":"ccm","adata":"","cipher":"aes","salt":"TznhuWAEAnE=","ct":"/cRuaFgamKh4e3cckElbPFXLiw=="}0000a5{"iv":"5meSIpyLG5BYJ6VXKuLLPg==","v":1,"iter":1000,"ks":128,"ts":64,"mode":"ccm","adata":"","cipher":"aes","salt":"1Oeswbcmoxs=","ct":"kAhdzaKoolPjT7DBqiHSZB9m2aU="}"#;
+        let key = "f4b7c3a9e1d2086b5a7c9e3f1d2b4a6c8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b";
+        assert_eq!(parse_encrypted_header(cipher_text).unwrap().get_size(), 45);
+        let chunks = decrypt_chunks(cipher_text, key).unwrap();
+        assert_eq!(chunks, vec!["First chunk", "Second chunk"]);
+    }
+
+    #[test]
+    fn unsupported_header_versions_are_reported() {
+        let cipher_text = "JED0200002a053336eb7a2472d9ae4a690a978fa8a46f12345678";
+        match parse_encrypted_header(cipher_text) {
+            Err(JoplinReaderError::UnsupportedHeaderVersion { version }) => assert_eq!(version, 2),
+            other => panic!("Expected an unsupported version, got {:?}", other),
+        }
+        let header = parse_encrypted_header_lenient(cipher_text).unwrap();
+        assert_eq!(header.get_version(), 2);
+        assert_eq!(header.get_master_key_id(), "3336eb7a2472d9ae4a690a978fa8a46f");
+        assert_eq!(header.get_size(), cipher_text.len());
+        assert_eq!(cipher_body_offset(cipher_text).unwrap(), cipher_text.len());
+        assert_eq!(cipher_body_offset("JED01000022053336eb7a2472d9ae4a690a978fa8a46f{}").unwrap(), 45);
+    }
+
+    #[test]
+    fn key_encryption_methods_are_rejected_for_items() {
+        let cipher_text = r#"JED01000022043336eb7a2472d9ae4a690a978fa8a46f00000b{"iv":"a="}"#;
+        match raw_chunks(cipher_text) {
+            Err(JoplinReaderError::UnexpectedEncryptionMethod { expected: 5, found: 4 }) => {}
+            other => panic!("Expected an unexpected encryption method, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn final_percent_decoding_can_be_disabled() {
+        let item = r#"id: 1b2c3d4e5f60718293a4b5c6d7e8f901
+encryption_cipher_text: JED01000022053336eb7a2472d9ae4a690a978fa8a46f0000f5{"iv":"YjTWP05NwoStCiBgiffE9Q==","v":1,"iter":1000,"ks":128,"ts":64,"mode":"ccm","adata":"","cipher":"aes","salt":"TznhuWAEAnE=","ct":"68hueElUj8oHQG9df49X4fSXtoM8bF7XVxcGiz1mE8XnlkBbEDLT/zeGPRab2dn9fRcHKmcuvQZpn/Hx41Dw98pUvxHYqsWNKYDIwZpF3A=="}
+encryption_applied: 1
+updated_time: 2021-03-20T10:00:00.000Z
+type_: 1"#;
+        let key = "f4b7c3a9e1d2086b5a7c9e3f1d2b4a6c8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b";
+        let mut store = MemoryStore::new();
+        store.insert("note.md", item);
+
+        // The chunk holds `%2541`, i.e. an escaped `%41`
+        let mut note = NoteInfo::from_store(&store, "note.md").unwrap();
+        assert_eq!(note.read_from(&store, Some(key)).unwrap(), "Use A for A");
+        // The cached content is read again with other options
+        let options = ReadOptions::default().percent_decode(false);
+        assert_eq!(note.read_with(&store, Some(key), &options).unwrap(), "Use %41 for A");
+        assert_eq!(note.read_from(&store, Some(key)).unwrap(), "Use A for A");
+    }
+
+    #[test]
+    fn escapes_decode_ascii_and_latin1() {
+        assert_eq!(decode_joplin_escapes("100%25 %3Cb%3E %E9t%E9"), "100% <b> été");
+    }
+
+    #[test]
+    fn escapes_decode_bmp_characters() {
+        assert_eq!(decode_joplin_escapes("%u00FC%u20ac %u65E5%u672C"), "ü€ 日本");
+    }
+
+    #[test]
+    fn escapes_combine_surrogate_pairs() {
+        assert_eq!(decode_joplin_escapes("%uD83D%uDE00 %ud834%udd1e"), "😀 𝄞");
+    }
+
+    #[test]
+    fn escapes_replace_lone_surrogates() {
+        assert_eq!(decode_joplin_escapes("a%uD83Db"), "a\u{FFFD}b");
+        assert_eq!(decode_joplin_escapes("a%uDE00b"), "a\u{FFFD}b");
+        // A low surrogate before a high one is not a pair
+        assert_eq!(decode_joplin_escapes("%uDE00%uD83D"), "\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn escapes_mixed_and_invalid() {
+        assert_eq!(decode_joplin_escapes("%u00E9%20%uD83D%uDE00%21"), "é 😀!");
+        // Incomplete escapes are kept as they are
+        assert_eq!(decode_joplin_escapes("100% %u12 %G1 %"), "100% %u12 %G1 %");
+        // Every escape is only decoded once
+        assert_eq!(decode_joplin_escapes("%2541 %25u0041"), "%41 %u0041");
+    }
+
+    #[test]
+    fn raw_chunks_are_not_decrypted() {
+        let cipher_text = r#"JED01000022053336eb7a2472d9ae4a690a978fa8a46f00000b{"iv":"a="}000006{"v":}"#;
+        assert_eq!(raw_chunks(cipher_text).unwrap(), vec![r#"{"iv":"a="}"#, r#"{"v":}"#]);
+        assert!(raw_chunks("JED01").is_err());
+    }
+
+    #[test]
+    fn read_picks_up_modified_files() {
+        let props = "id: 9a20a9e4d336de70cb6d22a58a3e673c\ntype_: 1\nencryption_applied: 0";
+        let mut store = MemoryStore::new();
+        store.insert("note.md", format!("Title\n\nFirst\n\n{}", props));
+        let mut note = NoteInfo::from_store(&store, "note.md").unwrap();
+        assert_eq!(note.read_from(&store, None).unwrap(), "First");
+        assert!(note.read_time.is_some());
+
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        store.insert("note.md", format!("Title\n\nSecond\n\n{}", props));
+        assert_eq!(note.read_from(&store, None).unwrap(), "Second");
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn fallback_encoding_decodes_legacy_content() {
+        let mut text = b"Title\n\nCaf".to_vec();
+        text.push(0xe9);
+        text.extend_from_slice(b"\n\nid: 9a20a9e4d336de70cb6d22a58a3e673c\ntype_: 1\nencryption_applied: 0");
+        let mut store = MemoryStore::new();
+        store.insert("note.md", text);
+        let mut note = NoteInfo::from_store(&store, "note.md").unwrap();
+        assert!(note.read_from(&store, None).is_err());
+
+        let options = ReadOptions::default().with_fallback_encoding(encoding_rs::WINDOWS_1252);
+        assert_eq!(note.read_with(&store, None, &options).unwrap(), "Caf\u{e9}");
+    }
+
+    #[test]
+    fn deserialize_accepts_a_single_title_line() {
+        let kv_store = NoteInfo::deserialize("Just a title".lines(), None).unwrap();
+        assert_eq!(kv_store.get("title").unwrap(), "Just a title");
+        assert_eq!(kv_store.get("body").unwrap(), "");
+
+        let kv_store = NoteInfo::deserialize("Title\n\nFirst line\nSecond line".lines(), None).unwrap();
+        assert_eq!(kv_store.get("title").unwrap(), "Title");
+        assert_eq!(kv_store.get("body").unwrap(), "First line\nSecond line");
+    }
+
+    #[test]
+    fn cipher_text_is_cut_out_of_the_file() {
+        let cipher_text = "JED01000022053336eb7a2472d9ae4a690a978fa8a46f000000";
+        let mut store = MemoryStore::new();
+        store.insert(
+            "note.md",
+            format!(
+                "id: 9a20a9e4d336de70cb6d22a58a3e673c\r\nencryption_cipher_text:  {} \r\nencryption_applied: 1\ntype_: 1",
+                cipher_text
+            ),
+        );
+        let note = NoteInfo::from_store(&store, "note.md").unwrap();
+        assert_eq!(note.get_encryption_key_id(), Some("3336eb7a2472d9ae4a690a978fa8a46f"));
+        assert_eq!(note.read_cipher_text(&store).unwrap(), cipher_text);
+    }
+
+    #[test]
+    fn only_an_empty_line_after_the_title_is_dropped() {
+        let kv_store = NoteInfo::deserialize("Title\n\nid: a\ntype_: 1".lines(), None).unwrap();
+        assert_eq!(kv_store.get("title").unwrap(), "Title");
+        assert_eq!(kv_store.get("body").unwrap(), "");
+
+        let kv_store = NoteInfo::deserialize("Title\n\nBody\n\nid: a\ntype_: 1".lines(), None).unwrap();
+        assert_eq!(kv_store.get("title").unwrap(), "Title");
+        assert_eq!(kv_store.get("body").unwrap(), "Body");
+
+        // Without the separator, the first line of the body is kept
+        let kv_store = NoteInfo::deserialize("Title\nBody\n\nid: a\ntype_: 1".lines(), None).unwrap();
+        assert_eq!(kv_store.get("title").unwrap(), "Title");
+        assert_eq!(kv_store.get("body").unwrap(), "Body");
+    }
+
+    #[test]
+    fn decrypted_notes_can_be_parsed() {
+        let properties = parse_decrypted_note("Title\n\nBody\n\nis_todo: 1\ntype_: 1").unwrap();
+        assert_eq!(properties.title(), Some("Title"));
+        assert_eq!(properties.body(), Some("Body"));
+        assert_eq!(properties.is_todo(), Some(true));
+        assert!(parse_decrypted_note("Title\n\nis_todo: 1").is_err());
+    }
+
+    #[cfg(feature = "api-json")]
+    #[test]
+    fn properties_with_missing_fields_can_be_serialized() {
+        let properties = parse_decrypted_note("Title\n\nBody\n\ntype_: 1").unwrap();
+        let json = serde_json::to_value(&properties).unwrap();
+        assert_eq!(json["title"], "Title");
+        assert_eq!(json["created_time"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn deserialize_falls_back_to_the_known_type() {
+        let text = "Title\n\nBody\n\nid: 9a20a9e4d336de70cb6d22a58a3e673c";
+        assert!(NoteInfo::deserialize(text.lines(), None).is_err());
+        let kv_store = NoteInfo::deserialize(text.lines(), Some(JoplinItemType::Note)).unwrap();
+        assert_eq!(kv_store.get("body").unwrap(), "Body");
+
+        // Without any properties, the known type decides whether there is a body
+        let kv_store = NoteInfo::deserialize("Work".lines(), Some(JoplinItemType::Folder)).unwrap();
+        assert_eq!(kv_store.get("title").unwrap(), "Work");
+        assert!(!kv_store.contains_key("body"));
+        let kv_store = NoteInfo::deserialize("Title\n\nBody".lines(), None).unwrap();
+        assert_eq!(kv_store.get("body").unwrap(), "Body");
+    }
+
+    #[test]
+    fn empty_note_body_is_not_an_error() {
+        let mut store = MemoryStore::new();
+        store.insert("note.md", "Title\n\nid: 9a20a9e4d336de70cb6d22a58a3e673c\ntype_: 1\nencryption_applied: 0");
+        store.insert("folder.md", "Folder\n\nid: 6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c\ntype_: 2\nencryption_applied: 0");
+
+        let mut note = NoteInfo::from_store(&store, "note.md").unwrap();
+        assert_eq!(note.read_from(&store, None).unwrap(), "");
+        let mut folder = NoteInfo::from_store(&store, "folder.md").unwrap();
+        match folder.read_from(&store, None) {
+            Err(JoplinReaderError::NoText) => {}
+            result => panic!("Expected no text, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn missing_encryption_applied_is_inferred_from_cipher_text() {
+        let header = "JED01000022053336eb7a2472d9ae4a690a978fa8a46f";
+        let note = note_from_text(&format!(
+            "id: 9a20a9e4d336de70cb6d22a58a3e673c\nencryption_cipher_text: {}\ntype_: 1",
+            header
+        ))
+        .unwrap();
+        assert!(note.is_encrypted());
+        assert!(note.is_encryption_inferred());
+        assert_eq!(note.get_encryption_key_id(), Some("3336eb7a2472d9ae4a690a978fa8a46f"));
+
+        let text = "id: 9a20a9e4d336de70cb6d22a58a3e673c\nencryption_cipher_text: garbage\ntype_: 1";
+        assert!(note_from_text(text).is_err());
+    }
+}
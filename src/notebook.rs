@@ -1,24 +1,483 @@
-use crate::key::{load_master_key, MasterKey};
-use crate::note::NoteInfo;
+use crate::key::{load_master_key_from_store, MasterKey};
+use crate::note::{
+    decrypt_chunks_with, parse_encrypted_header, parse_time, AlarmInfo, DecryptStats, JoplinItemType,
+    LineEnding, MarkupLanguage, NoteInfo, NoteProperties, ReadOptions,
+};
+#[cfg(feature = "tar")]
+use crate::store::MemoryStore;
+use crate::store::{FsStore, MultiStore, NoteStore};
 use crate::JoplinReaderError;
 
-use std::collections::HashMap;
-use std::fs;
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::ops::ControlFlow;
 use std::path::Path;
 
+use chrono::NaiveDateTime;
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
 use serde::Serialize;
 
+/// Returns whether `id` looks like a Joplin id, i.e. 32 hexadecimal
+/// characters (in any case).
+fn is_joplin_id(id: &str) -> bool {
+    id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
 /// Container `struct` which contains the references (and contents) to the
 /// [`NoteInfo`]s as well as the [`MasterKey`]s.
-#[derive(Debug, Serialize)]
-pub struct JoplinNotebook {
-    notes: HashMap<String, NoteInfo>,
-    master_keys: HashMap<String, MasterKey>,
+/// All files are accessed through a [`NoteStore`], by default the local
+/// filesystem ([`FsStore`]).
+#[derive(Serialize)]
+pub struct JoplinNotebook<S: NoteStore = FsStore> {
+    // Sorted maps, so iterating and serializing is reproducible
+    notes: BTreeMap<String, NoteInfo>,
+    master_keys: BTreeMap<String, MasterKey>,
+    #[serde(skip)]
+    store: S,
+    #[serde(skip)]
+    options: ReadOptions,
+    // Files which could not be parsed as items while loading
+    #[serde(skip)]
+    load_errors: Vec<(String, JoplinReaderError)>,
+    #[serde(skip)]
+    max_cached_notes: Option<usize>,
+    #[serde(skip)]
+    cache_index: CacheIndex,
+}
+
+impl<S: NoteStore + fmt::Debug> fmt::Debug for JoplinNotebook<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoplinNotebook")
+            .field("notes", &self.notes)
+            .field("master_keys", &RedactedKeys(&self.master_keys))
+            .field("store", &self.store)
+            .field("options", &self.options)
+            .field("load_errors", &self.load_errors)
+            .field("max_cached_notes", &self.max_cached_notes)
+            .finish()
+    }
+}
+
+/// Prints only the ids of the master keys, never the decrypted keys.
+struct RedactedKeys<'a>(&'a BTreeMap<String, MasterKey>);
+
+impl fmt::Debug for RedactedKeys<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(self.0.keys().map(|key_id| (key_id, "<redacted>")))
+            .finish()
+    }
+}
+
+/// Items whose content is cached, by when they were read last, so the least
+/// recently read ones can be dropped, see
+/// [`JoplinNotebookBuilder::max_cached_notes`].
+#[derive(Debug, Default)]
+struct CacheIndex {
+    by_access: BTreeMap<u64, String>,
+    by_id: HashMap<String, u64>,
+}
+
+impl CacheIndex {
+    /// Records that `note_id` was read, `access` being its
+    /// [`NoteInfo::get_last_access`].
+    fn touch(&mut self, note_id: &str, access: u64) {
+        if let Some(previous) = self.by_id.insert(note_id.to_string(), access) {
+            self.by_access.remove(&previous);
+        }
+        self.by_access.insert(access, note_id.to_string());
+    }
+
+    fn remove(&mut self, note_id: &str) {
+        if let Some(access) = self.by_id.remove(note_id) {
+            self.by_access.remove(&access);
+        }
+    }
+
+    /// Removes the least recently read item and returns its id.
+    fn pop_oldest(&mut self) -> Option<String> {
+        let (_, note_id) = self.by_access.pop_first()?;
+        self.by_id.remove(&note_id);
+        Some(note_id)
+    }
+
+    fn len(&self) -> usize {
+        self.by_access.len()
+    }
+}
+
+/// Overview of all items of a [`JoplinNotebook`], see
+/// [`JoplinNotebook::summary`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NotebookSummary {
+    /// Number of items
+    pub total_items: usize,
+    /// Number of items per type
+    pub items_per_type: HashMap<JoplinItemType, usize>,
+    /// Number of encrypted items
+    pub encrypted_items: usize,
+    /// Number of distinct master keys encrypted items refer to
+    pub master_keys_referenced: usize,
+    /// Oldest `updated_time` of all items
+    pub oldest_updated_time: Option<NaiveDateTime>,
+    /// Newest `updated_time` of all items
+    pub newest_updated_time: Option<NaiveDateTime>,
+}
+
+/// Matches a link to another item (`:/<id>`) in a note body
+static RESOURCE_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r":/([0-9a-f]{32})").unwrap());
+
+/// Files of a Joplin data folder which are never items and are skipped when
+/// loading. Entries starting with `*` match the end of a path, all others a
+/// file name or folder name.
+pub const DEFAULT_IGNORED_FILES: &[&str] = &[
+    "info.json",
+    "lock",
+    ".resource",
+    ".sync",
+    "*.sqlite",
+    "*.sqlite-journal",
+    "*.sqlite-wal",
+    "*.sqlite-shm",
+    "*.log",
+];
+
+/// Returns whether `path` matches one of the ignore `patterns`, see
+/// [`DEFAULT_IGNORED_FILES`].
+fn is_ignored(path: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| match pattern.strip_prefix('*') {
+        Some(suffix) => path.ends_with(suffix),
+        None => path.split('/').any(|component| component == pattern),
+    })
+}
+
+/// Feeds `bytes` into a 64 bit FNV-1a `hash`. Unlike the hashers of `std`,
+/// the result is the same across Rust versions and platforms, so it can be
+/// stored.
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// How a property of a note is represented in the Joplin data API
+#[cfg(feature = "api-json")]
+enum ApiType {
+    Text,
+    Int,
+    Numeric,
+    /// Milliseconds since the epoch
+    Time,
+}
+
+/// Properties of a note in the Joplin data API, see:
+/// https://joplinapp.org/api/references/rest_api/#properties
+#[cfg(feature = "api-json")]
+const API_NOTE_PROPERTIES: &[(&str, ApiType)] = &[
+    ("id", ApiType::Text),
+    ("parent_id", ApiType::Text),
+    ("title", ApiType::Text),
+    ("body", ApiType::Text),
+    ("created_time", ApiType::Time),
+    ("updated_time", ApiType::Time),
+    ("is_conflict", ApiType::Int),
+    ("latitude", ApiType::Numeric),
+    ("longitude", ApiType::Numeric),
+    ("altitude", ApiType::Numeric),
+    ("author", ApiType::Text),
+    ("source_url", ApiType::Text),
+    ("is_todo", ApiType::Int),
+    ("todo_due", ApiType::Time),
+    ("todo_completed", ApiType::Time),
+    ("source", ApiType::Text),
+    ("source_application", ApiType::Text),
+    ("application_data", ApiType::Text),
+    ("order", ApiType::Numeric),
+    ("user_created_time", ApiType::Time),
+    ("user_updated_time", ApiType::Time),
+    ("encryption_cipher_text", ApiType::Text),
+    ("encryption_applied", ApiType::Int),
+    ("markup_language", ApiType::Int),
+    ("is_shared", ApiType::Int),
+    ("share_id", ApiType::Text),
+    ("conflict_original_id", ApiType::Text),
+    ("master_key_id", ApiType::Text),
+    ("user_data", ApiType::Text),
+    ("deleted_time", ApiType::Time),
+];
+
+/// Result of [`JoplinNotebook::read_all`].
+#[derive(Debug, Default)]
+pub struct ReadAllResult {
+    /// Body of every note which was read, by id
+    pub notes: HashMap<String, String>,
+    /// Every note which could not be read together with the reason
+    pub failures: Vec<(String, JoplinReaderError)>,
+}
+
+/// A note matching the query of [`JoplinNotebook::search`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    pub note_id: String,
+    pub title: Option<String>,
+}
+
+/// A saved search (`Search` or `SmartFilter` item), see
+/// [`JoplinNotebook::saved_searches`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedSearch {
+    pub id: String,
+    pub type_: JoplinItemType,
+    /// Name of the search
+    pub title: Option<String>,
+    /// Query of the search, from the `query` (or `query_pattern`) property
+    pub query: Option<String>,
+}
+
+/// Everything about a single note, see [`JoplinNotebook::note_bundle`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct NoteBundle {
+    pub id: String,
+    pub title: Option<String>,
+    pub body: String,
+    /// All properties as they are serialized, see
+    /// [`JoplinNotebook::raw_properties`]
+    pub properties: BTreeMap<String, String>,
+    /// Tags of the note, ordered by tag id
+    pub tags: Vec<BundleItem>,
+    /// Ids of the resources linked in the body, in order of appearance
+    pub resource_ids: Vec<String>,
+    /// Folders from the top level folder down to the folder of the note
+    pub folder_path: Vec<BundleItem>,
+}
+
+/// An item referenced by a [`NoteBundle`]. The `title` is `None` if the item
+/// is missing or cannot be read.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct BundleItem {
+    pub id: String,
+    pub title: Option<String>,
+}
+
+/// Counts of the body of a note, see [`JoplinNotebook::note_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct NoteStats {
+    /// Number of characters (not bytes)
+    pub chars: usize,
+    /// Number of whitespace separated words
+    pub words: usize,
+    pub lines: usize,
+}
+
+impl NoteStats {
+    fn count(body: &str) -> NoteStats {
+        NoteStats {
+            chars: body.chars().count(),
+            words: body.split_whitespace().count(),
+            lines: body.lines().count(),
+        }
+    }
+}
+
+/// Metadata of a resource (attachment), see [`JoplinNotebook::resource_info`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResourceInfo {
+    pub title: Option<String>,
+    pub mime: Option<String>,
+    pub filename: Option<String>,
+    pub file_extension: Option<String>,
+    /// Size of the file in bytes
+    pub size: Option<u64>,
+    /// Whether the file is encrypted and needs to be decrypted before use
+    pub blob_encrypted: bool,
+}
+
+/// Problems found by [`JoplinNotebook::health_check`]. Every category lists
+/// the offending item ids (file paths for `unparsable_files` and
+/// `duplicate_ids`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HealthReport {
+    /// Files which could not be parsed as items
+    pub unparsable_files: Vec<String>,
+    /// Files which were not loaded, because another file has the same id
+    pub duplicate_ids: Vec<String>,
+    /// Encrypted items whose master key is not loaded
+    pub missing_keys: Vec<String>,
+    /// Items which cannot be read or decrypted although the key is loaded
+    pub decryption_failures: Vec<String>,
+    /// Items with a `*_time` property which is not a valid timestamp
+    pub malformed_timestamps: Vec<String>,
+    /// Notes and folders whose `parent_id` is not an existing folder
+    pub orphaned: Vec<String>,
+}
+
+impl HealthReport {
+    /// Whether no problems were found.
+    pub fn is_healthy(&self) -> bool {
+        *self == HealthReport::default()
+    }
+}
+
+/// Builder to create a [`JoplinNotebook`] with custom [`ReadOptions`].
+/// ```rust,no_run
+/// use joplin_reader::JoplinNotebook;
+///
+/// let notebook = JoplinNotebook::builder()
+///     .max_note_bytes(1024 * 1024)
+///     .build("./Joplin", vec!["3336eb7a2472d9ae4a690a978fa8a46f,plaintext_password"]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct JoplinNotebookBuilder {
+    options: ReadOptions,
+    ignored_files: Vec<String>,
+    id_prefix: String,
+    max_cached_notes: Option<usize>,
+}
+
+impl Default for JoplinNotebookBuilder {
+    fn default() -> Self {
+        Self {
+            options: ReadOptions::default(),
+            ignored_files: default_ignored_files(),
+            id_prefix: String::new(),
+            max_cached_notes: None,
+        }
+    }
+}
+
+fn default_ignored_files() -> Vec<String> {
+    DEFAULT_IGNORED_FILES.iter().map(|p| p.to_string()).collect()
+}
+
+impl JoplinNotebookBuilder {
+    pub fn new() -> JoplinNotebookBuilder {
+        JoplinNotebookBuilder::default()
+    }
+
+    /// Additionally skips files matching `pattern` when loading, see
+    /// [`DEFAULT_IGNORED_FILES`] for the syntax.
+    pub fn ignore_file(mut self, pattern: &str) -> Self {
+        self.ignored_files.push(pattern.to_string());
+        self
+    }
+
+    /// Replaces the patterns of files which are skipped when loading
+    /// ([`DEFAULT_IGNORED_FILES`] by default).
+    pub fn ignored_files<'a, I>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        self.ignored_files = patterns.into_iter().map(|p| p.to_string()).collect();
+        self
+    }
+
+    /// Only loads the items whose id starts with `id_prefix`, e.g. to split
+    /// a large data folder across several workers by the first hex digit.
+    /// Files are skipped by their name, before they are read.
+    pub fn id_prefix(mut self, id_prefix: &str) -> Self {
+        self.id_prefix = id_prefix.to_string();
+        self
+    }
+
+    /// Keeps the content of at most `max_cached_notes` items in memory. After
+    /// every read of an item the content of the least recently read items is
+    /// dropped, and read again when needed. This holds for calls which read
+    /// many items (e.g. [`JoplinNotebook::search`]) as well, and the item
+    /// read last is always kept. Unlimited by default.
+    pub fn max_cached_notes(mut self, max_cached_notes: usize) -> Self {
+        self.max_cached_notes = Some(max_cached_notes);
+        self
+    }
+
+    /// See [`ReadOptions::max_note_bytes`].
+    pub fn max_note_bytes(mut self, max_note_bytes: usize) -> Self {
+        self.options = self.options.max_note_bytes(max_note_bytes);
+        self
+    }
+
+    /// See [`ReadOptions::max_note_chunks`].
+    pub fn max_note_chunks(mut self, max_note_chunks: usize) -> Self {
+        self.options = self.options.max_note_chunks(max_note_chunks);
+        self
+    }
+
+    /// See [`ReadOptions::metadata_only`]. [`JoplinNotebook::read_note`] then
+    /// fails with [`JoplinReaderError::NoText`], as no body is read.
+    pub fn metadata_only(mut self, metadata_only: bool) -> Self {
+        self.options = self.options.metadata_only(metadata_only);
+        self
+    }
+
+    /// See [`ReadOptions::percent_decode`].
+    pub fn percent_decode(mut self, percent_decode: bool) -> Self {
+        self.options = self.options.percent_decode(percent_decode);
+        self
+    }
+
+    /// See [`ReadOptions::line_ending`].
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.options = self.options.line_ending(line_ending);
+        self
+    }
+
+    /// See [`ReadOptions::with_property_parser`].
+    pub fn with_property_parser<T, F>(mut self, key: &str, parser: F) -> Self
+    where
+        T: Any + Send + Sync,
+        F: Fn(&str) -> Option<T> + Send + Sync + 'static,
+    {
+        self.options = self.options.with_property_parser(key, parser);
+        self
+    }
+
+    /// See [`ReadOptions::with_fallback_encoding`].
+    #[cfg(feature = "encoding")]
+    pub fn with_fallback_encoding(mut self, encoding: &'static encoding_rs::Encoding) -> Self {
+        self.options = self.options.with_fallback_encoding(encoding);
+        self
+    }
+
+    /// Read a Joplin data folder, see [`JoplinNotebook::new`].
+    pub fn build<'a, P: AsRef<Path>, I>(
+        self,
+        joplin_folder: P,
+        passwords: I,
+    ) -> Result<JoplinNotebook, JoplinReaderError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        self.build_with_store(FsStore::new(joplin_folder), passwords)
+    }
+
+    /// Read a Joplin data folder from a [`NoteStore`], see
+    /// [`JoplinNotebook::with_store`].
+    pub fn build_with_store<'a, S: NoteStore, I>(
+        self,
+        store: S,
+        passwords: I,
+    ) -> Result<JoplinNotebook<S>, JoplinReaderError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let mut notebook =
+            JoplinNotebook::load(store, passwords, self.options, &self.ignored_files, &self.id_prefix)?;
+        notebook.max_cached_notes = self.max_cached_notes;
+
+        Ok(notebook)
+    }
 }
 
-impl JoplinNotebook {
+impl JoplinNotebook<FsStore> {
     /// Read a Joplin data folder. `passwords` need to be passed as comma-separated
-    /// key-value (master_key_id,passphrase) pairs.
+    /// key-value (master_key_id,passphrase) pairs, entries without a comma fail
+    /// with [`JoplinReaderError::InvalidFormat`], and a wrong passphrase with
+    /// the error of decrypting its master key.
     pub fn new<'a, P: AsRef<Path>, I>(
         joplin_folder: P,
         passwords: I,
@@ -26,55 +485,335 @@ impl JoplinNotebook {
     where
         I: IntoIterator<Item = &'a str>,
     {
-        let mut master_keys: HashMap<String, MasterKey> = HashMap::new();
+        JoplinNotebook::with_store(FsStore::new(joplin_folder), passwords)
+    }
+
+    /// Read only the items of a Joplin data folder whose id starts with
+    /// `id_prefix`, see [`JoplinNotebookBuilder::id_prefix`].
+    pub fn new_with_filter<'a, P: AsRef<Path>, I>(
+        joplin_folder: P,
+        passwords: I,
+        id_prefix: &str,
+    ) -> Result<JoplinNotebook, JoplinReaderError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        JoplinNotebook::builder().id_prefix(id_prefix).build(joplin_folder, passwords)
+    }
+
+    /// Read several Joplin data folders as one notebook, see [`MultiStore`].
+    /// If an item exists in more than one folder, the one of the later folder
+    /// is used.
+    pub fn new_multi<'a, P: AsRef<Path>, I>(
+        joplin_folders: &[P],
+        passwords: I,
+    ) -> Result<JoplinNotebook<MultiStore>, JoplinReaderError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let stores: Vec<FsStore> = joplin_folders.iter().map(FsStore::new).collect();
+        JoplinNotebook::with_store(MultiStore::new(stores), passwords)
+    }
+
+    /// Reads a JEX export (a tar archive of all items) from `reader`, without
+    /// extracting it first. The items are kept in a [`MemoryStore`], the
+    /// files of the resources under `resources/<id>.<ext>`.
+    #[cfg(feature = "tar")]
+    pub fn from_jex<'a, R: std::io::Read, I>(
+        reader: R,
+        passwords: I,
+    ) -> Result<JoplinNotebook<MemoryStore>, JoplinReaderError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        use std::io::Read;
+
+        let tar_error = |e: std::io::Error| JoplinReaderError::FileReadError {
+            message: format!("Failed to read JEX archive: {}", e),
+        };
+        let mut store = MemoryStore::new();
+        let mut archive = tar::Archive::new(reader);
+        for entry in archive.entries().map_err(tar_error)? {
+            let mut entry = entry.map_err(tar_error)?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path().map_err(tar_error)?;
+            let path = path.to_string_lossy().trim_start_matches("./").to_string();
+            let mut content: Vec<u8> = Vec::new();
+            entry.read_to_end(&mut content).map_err(tar_error)?;
+            store.insert(&path, content);
+        }
+
+        JoplinNotebookBuilder::new()
+            .ignore_file("resources")
+            .build_with_store(store, passwords)
+    }
+
+    /// Returns a [`JoplinNotebookBuilder`] to customize how items are read.
+    pub fn builder() -> JoplinNotebookBuilder {
+        JoplinNotebookBuilder::new()
+    }
+
+    /// Reads and decrypts a single note without loading the whole folder.
+    /// Only the note itself and the master key it is encrypted with are read.
+    pub fn read_single_note<'a, P: AsRef<Path>, I>(
+        joplin_folder: P,
+        note_id: &str,
+        passwords: I,
+    ) -> Result<String, JoplinReaderError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        JoplinNotebook::read_single_note_from_store(&FsStore::new(joplin_folder), note_id, passwords)
+    }
+}
+
+impl<S: NoteStore> JoplinNotebook<S> {
+    /// Read a Joplin data folder from a [`NoteStore`]. `passwords` need to be
+    /// passed as comma-separated key-value (master_key_id,passphrase) pairs.
+    pub fn with_store<'a, I>(store: S, passwords: I) -> Result<JoplinNotebook<S>, JoplinReaderError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        JoplinNotebook::load(store, passwords, ReadOptions::default(), &default_ignored_files(), "")
+    }
+
+    /// Same as [`JoplinNotebook::read_single_note`], but reads from a
+    /// [`NoteStore`]. `note_id` has to be a Joplin id (32 hexadecimal
+    /// characters), anything else fails with
+    /// [`JoplinReaderError::InvalidNoteId`] before a file is read, and
+    /// other items (e.g. folders) fail with
+    /// [`JoplinReaderError::WrongItemType`].
+    pub fn read_single_note_from_store<'a, I>(
+        store: &S,
+        note_id: &str,
+        passwords: I,
+    ) -> Result<String, JoplinReaderError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        // The id becomes a path, so only ids which cannot leave the folder
+        // are accepted
+        if !is_joplin_id(note_id) {
+            return Err(JoplinReaderError::InvalidNoteId {
+                id: note_id.to_string(),
+            });
+        }
+        let note_path = format!("{}.md", note_id.to_ascii_lowercase());
+        let mut note = match NoteInfo::from_store(store, &note_path) {
+            Ok(note) => note,
+            Err(JoplinReaderError::FileNotFound { .. }) => {
+                return Err(JoplinReaderError::NoteIdNotFound {
+                    note_id: note_id.to_string(),
+                })
+            }
+            Err(e) => return Err(e),
+        };
+        let wrong_item_type = |note: &NoteInfo| JoplinReaderError::WrongItemType {
+            expected: JoplinItemType::Note,
+            found: *note.get_type_(),
+        };
+        if !note.may_be(JoplinItemType::Note) {
+            return Err(wrong_item_type(&note));
+        }
+        let mut master_key: Option<MasterKey> = None;
+        if let Some(master_key_id) = note.get_encryption_key_id() {
+            for password in passwords.into_iter() {
+                let (key_id, passphrase) = JoplinNotebook::<S>::parse_password(password)?;
+                if key_id == master_key_id {
+                    master_key = Some(load_master_key_from_store(
+                        store,
+                        &format!("{}.md", key_id),
+                        key_id.to_string(),
+                        passphrase.to_string(),
+                    )?);
+                    break;
+                }
+            }
+            if master_key.is_none() {
+                return Err(JoplinReaderError::NoEncryptionKey {
+                    key: format!("{:?}", master_key_id),
+                });
+            }
+        }
+
+        note.read_properties_with(store, master_key.as_deref(), &ReadOptions::default())?;
+        if !note.may_be(JoplinItemType::Note) {
+            return Err(wrong_item_type(&note));
+        }
+
+        Ok(note.cached_body()?.to_string())
+    }
+
+    /// Splits a `master_key_id,passphrase` pair.
+    fn parse_password(password: &str) -> Result<(&str, &str), JoplinReaderError> {
+        let mut iter = password.splitn(2, ',');
+        let master_key_id = iter.next();
+        let key = iter.next();
+        match (master_key_id, key) {
+            (Some(master_key_id), Some(key)) => Ok((master_key_id, key)),
+            _ => Err(JoplinReaderError::InvalidFormat {
+                message: "password must be key_id,passphrase".to_string(),
+            }),
+        }
+    }
+
+    fn load<'a, I>(
+        store: S,
+        passwords: I,
+        options: ReadOptions,
+        ignored_files: &[String],
+        id_prefix: &str,
+    ) -> Result<JoplinNotebook<S>, JoplinReaderError>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        // List first, so an unreadable folder is reported before any key
+        let mut note_paths = store.list()?;
+        // Sorted, so of two files with the same id (e.g. `<id>.md` and
+        // `<id>.txt`) always the same one is loaded
+        note_paths.sort_unstable();
+        let mut master_keys: BTreeMap<String, MasterKey> = BTreeMap::new();
         for password in passwords.into_iter() {
-            let mut iter = password.splitn(2, ",");
-            let master_key_id = iter.next();
-            let key = iter.next();
-            if let (Some(master_key_id), Some(key)) = (master_key_id, key) {
-                let mut key_filename = master_key_id.to_string();
-                key_filename.push_str(".md");
-                let key_path = joplin_folder.as_ref().join(key_filename);
-                if key_path.is_file() {
-                    let mk = load_master_key(&key_path, master_key_id.to_string(), key.to_string());
-                    if let Ok(mk) = mk {
-                        master_keys.insert(master_key_id.to_string(), mk);
-                    }
-                } else {
-                    return Err(JoplinReaderError::NoEncryptionKey { key: format!("{:?}", key_path)});
+            let (master_key_id, key) = JoplinNotebook::<S>::parse_password(password)?;
+            let mut key_filename = master_key_id.to_string();
+            key_filename.push_str(".md");
+            let mk = load_master_key_from_store(
+                &store,
+                &key_filename,
+                master_key_id.to_string(),
+                key.to_string(),
+            );
+            match mk {
+                Ok(mk) => {
+                    master_keys.insert(master_key_id.to_string(), mk);
                 }
+                Err(JoplinReaderError::FileNotFound { .. }) => {
+                    return Err(JoplinReaderError::NoEncryptionKey { key: format!("{:?}", key_filename)});
+                }
+                Err(e) => return Err(e),
             }
         }
 
-        let note_paths = match fs::read_dir(joplin_folder) {
-            Ok(d) => d,
-            Err(_) => return Err(JoplinReaderError::FolderReadError),
-        };
-        let mut notes: HashMap<String, NoteInfo> = HashMap::new();
+        let mut notes: BTreeMap<String, NoteInfo> = BTreeMap::new();
+        let mut load_errors: Vec<(String, JoplinReaderError)> = Vec::new();
         for note_path in note_paths {
-            let note_path = note_path.expect("Unable to read path").path();
-            let note_path = Path::new(&note_path);
-
-            if note_path.is_file() {
-                let item_id = note_path.file_stem().unwrap_or_default();
-                if !master_keys.contains_key(item_id.to_str().unwrap_or_default()) {
-                    if let Ok(note) = NoteInfo::new(note_path) {
-                        match item_id.to_str() {
-                            Some(note_id) => {
-                                notes.insert(note_id.to_string(), note);
+            if is_ignored(&note_path, ignored_files) {
+                continue;
+            }
+            let item_id = Path::new(&note_path).file_stem().unwrap_or_default();
+            if !item_id.to_str().unwrap_or_default().starts_with(id_prefix) {
+                continue;
+            }
+            if !master_keys.contains_key(item_id.to_str().unwrap_or_default()) {
+                match NoteInfo::from_store(&store, &note_path) {
+                    Ok(note) => {
+                        // Master keys without a passphrase are not items either
+                        if *note.get_type_() == JoplinItemType::MasterKey {
+                            continue;
+                        }
+                        if let Some(note_id) = item_id.to_str() {
+                            match notes.get(note_id) {
+                                Some(loaded) => load_errors.push((
+                                    note_path.to_string(),
+                                    JoplinReaderError::DuplicateNoteId {
+                                        id: note_id.to_string(),
+                                        path: loaded.get_path().to_string_lossy().to_string(),
+                                    },
+                                )),
+                                None => {
+                                    notes.insert(note_id.to_string(), note);
+                                }
                             }
-                            None => {}
                         }
                     }
+                    Err(e) => load_errors.push((note_path, e)),
                 }
             }
         }
 
-        Ok(JoplinNotebook { notes, master_keys })
+        Ok(JoplinNotebook {
+            notes,
+            master_keys,
+            store,
+            options,
+            load_errors,
+            max_cached_notes: None,
+            cache_index: CacheIndex::default(),
+        })
     }
 
-    /// Returns the content of a note.
-    pub fn read_note(&mut self, note_id: &str) -> Result<&str, JoplinReaderError> {
+    /// Loads the master key `key_id` with `passphrase` after the notebook was
+    /// created, e.g. after asking the user for it because reading a note
+    /// failed with [`JoplinReaderError::NoEncryptionKey`]. Items encrypted
+    /// with the key can be read right away. An already loaded key with the
+    /// same id is replaced.
+    pub fn add_key(&mut self, key_id: &str, passphrase: &str) -> Result<(), JoplinReaderError> {
+        let key_filename = format!("{}.md", key_id);
+        let master_key = match load_master_key_from_store(
+            &self.store,
+            &key_filename,
+            key_id.to_string(),
+            passphrase.to_string(),
+        ) {
+            Ok(master_key) => master_key,
+            Err(JoplinReaderError::FileNotFound { .. }) => {
+                return Err(JoplinReaderError::NoEncryptionKey { key: format!("{:?}", key_filename) });
+            }
+            Err(e) => return Err(e),
+        };
+        self.master_keys.insert(key_id.to_string(), master_key);
+
+        Ok(())
+    }
+
+    /// Adds a master key which was already decrypted elsewhere (e.g. kept in
+    /// the OS keychain), so no passphrase is needed. `key` are the raw bytes
+    /// of the master key, which Joplin stores hex encoded. An already loaded
+    /// key with the same id is replaced.
+    pub fn add_raw_key(&mut self, key_id: &str, key: Vec<u8>) {
+        self.master_keys.insert(key_id.to_string(), hex::encode(key));
+    }
+
+    /// Returns the paths of all files which could not be parsed as items
+    /// while loading, together with the reason. Files with the id of an
+    /// already loaded item are reported with
+    /// [`JoplinReaderError::DuplicateNoteId`].
+    pub fn load_errors(&self) -> &[(String, JoplinReaderError)] {
+        &self.load_errors
+    }
+
+    /// Returns the [`NoteStore`] the items are read from.
+    pub fn get_store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the [`ReadOptions`] used to read the items.
+    pub fn get_read_options(&self) -> &ReadOptions {
+        &self.options
+    }
+
+    /// Returns how many items keep their content in memory, see
+    /// [`JoplinNotebookBuilder::max_cached_notes`].
+    pub fn get_max_cached_notes(&self) -> Option<usize> {
+        self.max_cached_notes
+    }
+
+    /// Returns the ids of all items of the type `type_`. For encrypted items
+    /// this is the type outside of the cipher text.
+    fn ids_of_type(&self, type_: JoplinItemType) -> Vec<String> {
+        self.notes
+            .iter()
+            .filter(|(_, note)| *note.get_type_() == type_)
+            .map(|(note_id, _)| note_id.to_string())
+            .collect()
+    }
+
+    /// Reads (and decrypts) the item `note_id` into its cache and returns
+    /// its properties. All reads of the notebook go through this, so
+    /// [`JoplinNotebookBuilder::max_cached_notes`] is kept after every one.
+    fn read_cached(&mut self, note_id: &str) -> Result<&NoteProperties, JoplinReaderError> {
         let note = match self.notes.get_mut(note_id) {
             Some(note) => note,
             None => {
@@ -83,38 +822,3041 @@ impl JoplinNotebook {
                 })
             }
         };
-        let mut encryption_key: Option<&str> = None;
-        if note.is_encrypted() {
-            let master_key_id = match note.get_encryption_key_id() {
-                Some(key_id) => key_id.to_string(),
-                None => {
-                    return Err(JoplinReaderError::NoEncryptionKey {key: format!("{:?}", note.get_encryption_key_id())});
-                }
-            };
+        let encryption_key = JoplinNotebook::<S>::encryption_key_for(&self.master_keys, note, &self.options)?;
+        note.read_properties_with(&self.store, encryption_key, &self.options)?;
+        self.track_cached(note_id);
 
-            encryption_key = match self.master_keys.get(&master_key_id) {
-                Some(master_key) => Some(master_key.as_str()),
-                None => {
-                    return Err(JoplinReaderError::NoEncryptionKey {key: format!("{:?}", master_key_id)});
-                }
+        Ok(self.notes[note_id].get_content())
+    }
+
+    /// Same as [`JoplinNotebook::read_cached`], but returns the body like
+    /// [`NoteInfo::read_with`].
+    fn read_cached_body(&mut self, note_id: &str) -> Result<&str, JoplinReaderError> {
+        self.read_cached(note_id)?;
+
+        self.notes[note_id].cached_body()
+    }
+
+    /// Records that `note_id` was just read, and drops the content of the
+    /// least recently read items while more than `max_cached_notes` are
+    /// cached.
+    fn track_cached(&mut self, note_id: &str) {
+        let max_cached_notes = match self.max_cached_notes {
+            // The item read last is always kept
+            Some(max_cached_notes) => max_cached_notes.max(1),
+            None => return,
+        };
+        match self.notes.get(note_id) {
+            Some(note) if note.is_cached() => self.cache_index.touch(note_id, note.get_last_access()),
+            _ => self.cache_index.remove(note_id),
+        }
+        while self.cache_index.len() > max_cached_notes {
+            if let Some(note) = self.cache_index.pop_oldest().and_then(|id| self.notes.get_mut(&id)) {
+                note.invalidate();
             }
         }
+    }
+
+    /// Looks up the [`MasterKey`] needed to decrypt `note`. Returns `None` for
+    /// unencrypted notes, and for all notes when only reading metadata.
+    fn encryption_key_for<'k>(
+        master_keys: &'k BTreeMap<String, MasterKey>,
+        note: &NoteInfo,
+        options: &ReadOptions,
+    ) -> Result<Option<&'k str>, JoplinReaderError> {
+        if !note.is_encrypted() || options.is_metadata_only() {
+            return Ok(None);
+        }
+        let master_key_id = match note.get_encryption_key_id() {
+            Some(key_id) => key_id.to_string(),
+            None => {
+                return Err(JoplinReaderError::NoEncryptionKey {key: format!("{:?}", note.get_encryption_key_id())});
+            }
+        };
 
-        note.read(encryption_key)
+        match master_keys.get(&master_key_id) {
+            Some(master_key) => Ok(Some(master_key.as_str())),
+            None => Err(JoplinReaderError::NoEncryptionKey {key: format!("{:?}", master_key_id)}),
+        }
     }
 
-    /// Returns a [`NoteInfo`]
-    pub fn get_note(&self, note_id: &str) -> Result<&NoteInfo, JoplinReaderError> {
-        match self.notes.get(note_id) {
-            Some(note) => Ok(note),
-            None => Err(JoplinReaderError::NoteIdNotFound {
-                note_id: note_id.to_string(),
-            }),
+    /// Returns the content of a note. Other items (e.g. folders) fail with
+    /// [`JoplinReaderError::WrongItemType`], and with
+    /// [`ReadOptions::metadata_only`] all notes fail with
+    /// [`JoplinReaderError::NoText`].
+    pub fn read_note(&mut self, note_id: &str) -> Result<&str, JoplinReaderError> {
+        let note_id = self.find_note_id(note_id)?;
+        let wrong_item_type = |note: &NoteInfo| JoplinReaderError::WrongItemType {
+            expected: JoplinItemType::Note,
+            found: *note.get_type_(),
+        };
+        if !self.notes[&note_id].may_be(JoplinItemType::Note) {
+            return Err(wrong_item_type(&self.notes[&note_id]));
         }
+        self.read_cached(&note_id)?;
+        let note = &self.notes[&note_id];
+        if !note.may_be(JoplinItemType::Note) {
+            return Err(wrong_item_type(note));
+        }
+
+        note.cached_body()
     }
 
-    /// Iterate all item Ids stored
-    pub fn iter(&self) -> impl Iterator<Item = &String> {
-        self.notes.keys()
+    /// Returns the content of the note a Joplin link points to. `link` is
+    /// either a bare id or a `:/<id>` link, optionally with an anchor
+    /// (`:/<id>#heading`), which is ignored.
+    pub fn read_linked_note(&mut self, link: &str) -> Result<&str, JoplinReaderError> {
+        let note_id = link.strip_prefix(":/").unwrap_or(link);
+        let note_id = note_id.split('#').next().unwrap_or_default();
+        self.read_note(note_id)
+    }
+
+    /// Appends the content of a note to `buf`, so one buffer can be reused
+    /// for many notes. Fails like [`JoplinNotebook::read_note`], in which
+    /// case `buf` is left unchanged.
+    pub fn read_note_into(&mut self, note_id: &str, buf: &mut String) -> Result<(), JoplinReaderError> {
+        buf.push_str(self.read_note(note_id)?);
+
+        Ok(())
+    }
+
+    /// Returns all properties of an item (title, body, ...) at once. They are
+    /// cached like the body returned by [`JoplinNotebook::read_note`].
+    pub fn read_full(&mut self, note_id: &str) -> Result<&NoteProperties, JoplinReaderError> {
+        let note_id = self.find_note_id(note_id)?;
+
+        self.read_cached(&note_id)
+    }
+
+    /// Reads (and decrypts) the items `note_ids` into their cache, so later
+    /// reads of them return right away. Returns whether reading each
+    /// succeeded, in the given order.
+    pub fn preload(&mut self, note_ids: &[&str]) -> Vec<(String, Result<(), JoplinReaderError>)> {
+        note_ids
+            .iter()
+            .map(|note_id| (note_id.to_string(), self.read_full(note_id).map(|_| ())))
+            .collect()
+    }
+
+    /// Returns the content of a note, decrypted with `key` instead of the
+    /// loaded master key. The note is always read again, which is useful to
+    /// verify a new key before rotating it. The content is not cached, so
+    /// later reads use the loaded master key again. Other items (e.g.
+    /// folders) fail with [`JoplinReaderError::WrongItemType`].
+    pub fn read_note_with_key(&mut self, note_id: &str, key: &str) -> Result<&str, JoplinReaderError> {
+        let note_id = self.find_note_id(note_id)?;
+        let note = match self.notes.get_mut(&note_id) {
+            Some(note) => note,
+            None => {
+                return Err(JoplinReaderError::NoteIdNotFound {
+                    note_id: note_id.to_string(),
+                })
+            }
+        };
+        let wrong_item_type = |note: &NoteInfo| JoplinReaderError::WrongItemType {
+            expected: JoplinItemType::Note,
+            found: *note.get_type_(),
+        };
+        if !note.may_be(JoplinItemType::Note) {
+            return Err(wrong_item_type(note));
+        }
+        note.invalidate();
+        let reading = note.read_properties_with(&self.store, Some(key), &self.options).map(|_| ());
+        note.mark_stale();
+        self.track_cached(&note_id);
+        reading?;
+        let note = &self.notes[&note_id];
+        if !note.may_be(JoplinItemType::Note) {
+            return Err(wrong_item_type(note));
+        }
+
+        note.cached_body()
+    }
+
+    /// Returns the content of a note with every resource link (`:/<id>`)
+    /// replaced by the path of the resource file in `resource_dir`, i.e.
+    /// `resource_dir/<id>.<ext>`. The extension is taken from the
+    /// `file_extension` of the resource item, decrypting it if needed. Links
+    /// to resources which cannot be read keep the bare id as file name.
+    pub fn read_note_with_resource_paths(
+        &mut self,
+        note_id: &str,
+        resource_dir: &str,
+    ) -> Result<String, JoplinReaderError> {
+        let body = self.read_note(note_id)?.to_string();
+
+        let mut extensions: HashMap<String, Option<String>> = HashMap::new();
+        for captures in RESOURCE_LINK.captures_iter(&body) {
+            let resource_id = captures[1].to_string();
+            if extensions.contains_key(&resource_id) {
+                continue;
+            }
+            let extension = self.resource_extension(&resource_id);
+            extensions.insert(resource_id, extension);
+        }
+
+        let resource_dir = resource_dir.trim_end_matches('/');
+        let body = RESOURCE_LINK.replace_all(&body, |captures: &Captures| {
+            let resource_id = &captures[1];
+            match extensions.get(resource_id) {
+                Some(Some(extension)) => format!("{}/{}.{}", resource_dir, resource_id, extension),
+                _ => format!("{}/{}", resource_dir, resource_id),
+            }
+        });
+
+        Ok(body.into_owned())
+    }
+
+    /// Parses the body of a note into Markdown events. Joplin links to notes
+    /// and resources (`:/<id>`) are rewritten to `joplin://<id>`, as used by
+    /// the Joplin renderer, so they are easy to tell apart from other links.
+    #[cfg(feature = "markdown")]
+    pub fn note_markdown_events(
+        &mut self,
+        note_id: &str,
+    ) -> Result<Vec<pulldown_cmark::Event<'static>>, JoplinReaderError> {
+        use pulldown_cmark::{CowStr, Event, Parser, Tag};
+
+        fn joplin_url(dest_url: CowStr<'static>) -> CowStr<'static> {
+            match dest_url.strip_prefix(":/") {
+                Some(id) if id.len() >= 32 && id.as_bytes()[..32].iter().all(u8::is_ascii_hexdigit) => {
+                    format!("joplin://{}", id).into()
+                }
+                _ => dest_url,
+            }
+        }
+
+        let body = self.read_note(note_id)?;
+        let events = Parser::new(body)
+            .map(|event| match event.into_static() {
+                Event::Start(Tag::Link { link_type, dest_url, title, id }) => Event::Start(Tag::Link {
+                    link_type,
+                    dest_url: joplin_url(dest_url),
+                    title,
+                    id,
+                }),
+                Event::Start(Tag::Image { link_type, dest_url, title, id }) => Event::Start(Tag::Image {
+                    link_type,
+                    dest_url: joplin_url(dest_url),
+                    title,
+                    id,
+                }),
+                event => event,
+            })
+            .collect();
+
+        Ok(events)
+    }
+
+    /// Renders the body of a note as HTML, with all resources inlined as
+    /// `data:` URIs: images are embedded, links to other resources become
+    /// download links. Links to resources which cannot be read are kept.
+    #[cfg(feature = "markdown")]
+    pub fn read_note_html_inline(&mut self, note_id: &str) -> Result<String, JoplinReaderError> {
+        use pulldown_cmark::{html, CowStr, Event, Parser, Tag, TagEnd};
+
+        fn escape_attribute(value: &str) -> String {
+            value
+                .replace('&', "&amp;")
+                .replace('"', "&quot;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+        }
+        // The MIME type comes from the resource, so anything which is not a
+        // plain `type/subtype` is not trusted
+        fn is_mime_type(mime: &str) -> bool {
+            let is_token = |part: &str| {
+                !part.is_empty()
+                    && part
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || "!#$&^_.+-".contains(c))
+            };
+            match mime.split_once('/') {
+                Some((type_, subtype)) => is_token(type_) && is_token(subtype),
+                None => false,
+            }
+        }
+
+        let body = self.read_note(note_id)?.to_string();
+
+        // Data URI and file name of every resource which could be read
+        let mut resources: HashMap<String, (String, String)> = HashMap::new();
+        for captures in RESOURCE_LINK.captures_iter(&body) {
+            let resource_id = captures[1].to_string();
+            if resources.contains_key(&resource_id) {
+                continue;
+            }
+            let info = match self.resource_info(&resource_id) {
+                Ok(info) => info,
+                Err(_) => continue,
+            };
+            if let Ok(data) = self.read_resource(&resource_id) {
+                let mime = info
+                    .mime
+                    .as_deref()
+                    .filter(|mime| is_mime_type(mime))
+                    .unwrap_or("application/octet-stream");
+                let data_uri = format!("data:{};base64,{}", mime, base64::encode(data));
+                let filename = info.filename.or(info.title).unwrap_or_else(|| resource_id.to_string());
+                resources.insert(resource_id, (data_uri, filename));
+            }
+        }
+        let resource_for = |dest_url: &str| {
+            let resource_id = dest_url.strip_prefix(":/")?;
+            resources.get(resource_id)
+        };
+
+        // Whether each open link was replaced by a download link
+        let mut links: Vec<bool> = Vec::new();
+        let events = Parser::new(&body).map(|event| match event {
+            Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+                let dest_url = match resource_for(&dest_url) {
+                    Some((data_uri, _)) => CowStr::from(data_uri.to_string()),
+                    None => dest_url,
+                };
+                Event::Start(Tag::Image { link_type, dest_url, title, id })
+            }
+            Event::Start(Tag::Link { link_type, dest_url, title, id }) => match resource_for(&dest_url) {
+                Some((data_uri, filename)) => {
+                    links.push(true);
+                    Event::Html(
+                        format!(
+                            "<a href=\"{}\" download=\"{}\">",
+                            escape_attribute(data_uri),
+                            escape_attribute(filename)
+                        )
+                        .into(),
+                    )
+                }
+                None => {
+                    links.push(false);
+                    Event::Start(Tag::Link { link_type, dest_url, title, id })
+                }
+            },
+            Event::End(TagEnd::Link) => match links.pop() {
+                Some(true) => Event::Html("</a>".into()),
+                _ => Event::End(TagEnd::Link),
+            },
+            event => event,
+        });
+        let mut output = String::new();
+        html::push_html(&mut output, events);
+
+        Ok(output)
+    }
+
+    /// Returns the content of the file of the resource `resource_id`,
+    /// decrypted if needed. The file is looked up in `.resource/<id>` (data
+    /// folders) and `resources/<id>.<ext>` (JEX exports).
+    pub fn read_resource(&mut self, resource_id: &str) -> Result<Vec<u8>, JoplinReaderError> {
+        let info = self.resource_info(resource_id)?;
+        let resource_id = self.find_note_id(resource_id)?;
+        let mut file = self.store.read_file(&format!(".resource/{}", resource_id));
+        if let (Err(_), Some(extension)) = (&file, &info.file_extension) {
+            file = self.store.read_file(&format!("resources/{}.{}", resource_id, extension));
+        }
+        let file = file?;
+        if !info.blob_encrypted {
+            return Ok(file);
+        }
+
+        let cipher_text = match String::from_utf8(file) {
+            Ok(cipher_text) => cipher_text,
+            Err(_) => {
+                return Err(JoplinReaderError::DecryptionError {
+                    message: "Encrypted text is not ascii".to_string(),
+                })
+            }
+        };
+        let master_key_id = parse_encrypted_header(&cipher_text)?.get_master_key_id().to_string();
+        let master_key = match self.master_keys.get(&master_key_id) {
+            Some(master_key) => master_key,
+            None => return Err(JoplinReaderError::NoEncryptionKey { key: format!("{:?}", master_key_id) }),
+        };
+        // Every chunk is a separately base64 encoded part of the file
+        let mut data: Vec<u8> = Vec::new();
+        for chunk in decrypt_chunks_with(&cipher_text, master_key, &self.options)? {
+            match base64::decode(chunk) {
+                Ok(mut bytes) => data.append(&mut bytes),
+                Err(_) => {
+                    return Err(JoplinReaderError::DecryptionError {
+                        message: "Failed to base64 decode resource".to_string(),
+                    })
+                }
+            }
+        }
+
+        Ok(data)
+    }
+
+    /// Returns the metadata of the resource `resource_id`. Only the resource
+    /// item is read (and decrypted), not the file itself.
+    pub fn resource_info(&mut self, resource_id: &str) -> Result<ResourceInfo, JoplinReaderError> {
+        let resource_id = self.find_note_id(resource_id)?;
+        let properties = self.read_cached(&resource_id)?;
+        let info = ResourceInfo {
+            title: properties.title().map(|v| v.to_string()),
+            mime: properties.mime().map(|v| v.to_string()),
+            filename: properties.filename().map(|v| v.to_string()),
+            file_extension: properties.file_extension().map(|v| v.to_string()),
+            size: properties.size(),
+            blob_encrypted: properties.encryption_blob_encrypted().unwrap_or(false),
+        };
+        let resource = &self.notes[&resource_id];
+        if !resource.may_be(JoplinItemType::Resource) {
+            return Err(JoplinReaderError::WrongItemType {
+                expected: JoplinItemType::Resource,
+                found: *resource.get_type_(),
+            });
+        }
+
+        Ok(info)
+    }
+
+    /// Returns the title of the folder `folder_id`, decrypting it if needed.
+    pub fn folder_title(&mut self, folder_id: &str) -> Result<String, JoplinReaderError> {
+        let title = self.read_full(folder_id)?.title().map(|title| title.to_string());
+        let folder = self.get_note(folder_id)?;
+        if !folder.may_be(JoplinItemType::Folder) {
+            return Err(JoplinReaderError::WrongItemType {
+                expected: JoplinItemType::Folder,
+                found: *folder.get_type_(),
+            });
+        }
+
+        title.ok_or(JoplinReaderError::NoText)
+    }
+
+    /// Collects everything about the note `note_id` into one [`NoteBundle`],
+    /// e.g. for archiving it as JSON. Only reading the note itself can fail,
+    /// tags and folders which are missing or cannot be read are kept without
+    /// a title, and so are links to missing resources.
+    pub fn note_bundle(&mut self, note_id: &str) -> Result<NoteBundle, JoplinReaderError> {
+        let note_id = self.find_note_id(note_id)?;
+        let body = self.read_note(&note_id)?.to_string();
+        let title = self.get_note(&note_id)?.get_content().title().map(|title| title.to_string());
+        let properties: BTreeMap<String, String> = self.raw_properties(&note_id)?.into_iter().collect();
+
+        self.read_note_tags();
+        let mut tag_ids: Vec<String> = self
+            .note_tags()
+            .into_iter()
+            .filter(|(tagged_note, _)| *tagged_note == note_id)
+            .map(|(_, tag_id)| tag_id)
+            .collect();
+        tag_ids.sort_unstable();
+        tag_ids.dedup();
+        let tags = tag_ids
+            .into_iter()
+            .map(|id| {
+                let title = self.item_properties(&id).and_then(|properties| properties.get("title").cloned());
+                BundleItem { id, title }
+            })
+            .collect();
+
+        let mut resource_ids: Vec<String> = Vec::new();
+        for captures in RESOURCE_LINK.captures_iter(&body) {
+            let id = captures[1].to_string();
+            // Links to other notes are skipped, but not ones to missing items
+            let is_resource = match self.item_properties(&id) {
+                Some(properties) => properties.get("type_").map(String::as_str) == Some("4"),
+                None => true,
+            };
+            if is_resource && !resource_ids.contains(&id) {
+                resource_ids.push(id);
+            }
+        }
+
+        let mut folder_path: Vec<BundleItem> = Vec::new();
+        let mut parent_id = properties.get("parent_id").cloned();
+        while let Some(folder_id) = parent_id.take().filter(|id| !id.is_empty()) {
+            // Guards against parent loops in corrupt data folders
+            if folder_path.iter().any(|folder| folder.id == folder_id) {
+                break;
+            }
+            let folder = self.item_properties(&folder_id);
+            parent_id = folder.as_ref().and_then(|folder| folder.get("parent_id").cloned());
+            folder_path.push(BundleItem {
+                id: folder_id,
+                title: folder.and_then(|mut folder| folder.remove("title")),
+            });
+        }
+        folder_path.reverse();
+
+        Ok(NoteBundle {
+            id: note_id,
+            title,
+            body,
+            properties,
+            tags,
+            resource_ids,
+            folder_path,
+        })
+    }
+
+    /// Reads (and decrypts) all properties of the item `item_id`, or returns
+    /// `None` if it is missing or cannot be read. Nothing is cached.
+    fn item_properties(&self, item_id: &str) -> Option<HashMap<String, String>> {
+        let item = self.notes.get(item_id)?;
+        let encryption_key = JoplinNotebook::<S>::encryption_key_for(&self.master_keys, item, &self.options).ok()?;
+        item.read_raw_properties_with(&self.store, encryption_key, &self.options).ok()
+    }
+
+    /// Reads (and decrypts) the encrypted items which may be `NoteTag`s into
+    /// their cache, so [`JoplinNotebook::note_tags`] does not decrypt them
+    /// again, and skips the other items from then on.
+    fn read_note_tags(&mut self) {
+        let item_ids: Vec<String> = self
+            .notes
+            .iter()
+            .filter(|(_, item)| item.is_encrypted() && item.may_be(JoplinItemType::NoteTag))
+            .map(|(item_id, _)| item_id.to_string())
+            .collect();
+        for item_id in item_ids {
+            // Items which cannot be read are skipped by `note_tags`
+            let _ = self.read_cached(&item_id);
+        }
+    }
+
+    /// Returns the (note id, tag id) pairs of all `NoteTag` items. Encrypted
+    /// ones are taken from their cache if they were read and decrypted
+    /// otherwise, the ones which cannot be read are skipped. Items known not
+    /// to be `NoteTag`s are not read at all.
+    fn note_tags(&self) -> Vec<(String, String)> {
+        let mut note_tags: Vec<(String, String)> = Vec::new();
+        for (item_id, item) in self.notes.iter() {
+            if !item.may_be(JoplinItemType::NoteTag) {
+                continue;
+            }
+            if !item.is_encrypted() {
+                if let (Some(note_id), Some(tag_id)) = (item.get_note_id(), item.get_tag_id()) {
+                    note_tags.push((note_id.to_string(), tag_id.to_string()));
+                }
+                continue;
+            }
+            let properties = if item.is_cached() {
+                Cow::Borrowed(item.get_content().extra_properties())
+            } else {
+                match self.item_properties(item_id) {
+                    Some(properties) if properties.get("type_").map(String::as_str) == Some("6") => {
+                        Cow::Owned(properties)
+                    }
+                    _ => continue,
+                }
+            };
+            if let (Some(note_id), Some(tag_id)) = (properties.get("note_id"), properties.get("tag_id")) {
+                note_tags.push((note_id.to_string(), tag_id.to_string()));
+            }
+        }
+
+        note_tags
+    }
+
+    /// Reads the `file_extension` of the resource item `resource_id`.
+    fn resource_extension(&mut self, resource_id: &str) -> Option<String> {
+        if *self.notes.get(resource_id)?.get_type_() != JoplinItemType::Resource {
+            return None;
+        }
+        let properties = self.read_cached(resource_id).ok()?;
+        match properties.file_extension() {
+            Some(extension) if !extension.is_empty() => Some(extension.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Returns all properties of a note as they are serialized, including
+    /// the ones [`NoteProperties`] does not model. The note is decrypted if
+    /// needed.
+    pub fn raw_properties(&self, note_id: &str) -> Result<HashMap<String, String>, JoplinReaderError> {
+        let note = self.get_note(note_id)?;
+        let encryption_key = JoplinNotebook::<S>::encryption_key_for(&self.master_keys, note, &self.options)?;
+
+        note.read_raw_properties_with(&self.store, encryption_key, &self.options)
+    }
+
+    /// Returns the content of the file of an item exactly as it is stored,
+    /// without parsing or decrypting it, e.g. to copy it unchanged.
+    pub fn raw_file(&self, note_id: &str) -> Result<Vec<u8>, JoplinReaderError> {
+        let note = self.get_note(note_id)?;
+
+        self.store.read_file(&note.get_path().to_string_lossy())
+    }
+
+    /// Returns the raw decrypted bytes of a note, before any text decoding is
+    /// applied. See [`NoteInfo::read_bytes_with`].
+    pub fn read_note_bytes(&self, note_id: &str) -> Result<Vec<u8>, JoplinReaderError> {
+        let note = self.get_note(note_id)?;
+        let encryption_key = JoplinNotebook::<S>::encryption_key_for(&self.master_keys, note, &self.options)?;
+
+        note.read_bytes_with(&self.store, encryption_key, &self.options)
+    }
+
+    /// Decrypts a note and returns how many cipher chunks it consists of and
+    /// how many bytes were decrypted.
+    pub fn note_decrypt_stats(&self, note_id: &str) -> Result<DecryptStats, JoplinReaderError> {
+        let note = self.get_note(note_id)?;
+        let encryption_key = JoplinNotebook::<S>::encryption_key_for(&self.master_keys, note, &self.options)?;
+
+        note.decrypt_stats_from(&self.store, encryption_key, &self.options)
+    }
+
+    /// Returns the ids of all notes created by the application `app` (see
+    /// [`NoteProperties::source_application`]). Notes which cannot be read
+    /// are skipped.
+    ///
+    /// [`NoteProperties::source_application`]: crate::note::NoteProperties::source_application
+    pub fn notes_from_application(&mut self, app: &str) -> Vec<String> {
+        let mut note_ids: Vec<String> = Vec::new();
+        for note_id in self.ids_of_type(JoplinItemType::Note) {
+            if let Ok(properties) = self.read_cached(&note_id) {
+                if properties.source_application() == Some(app) {
+                    note_ids.push(note_id);
+                }
+            }
+        }
+
+        note_ids
+    }
+
+    /// Counts the notes by their [`NoteProperties::markup_language`], where
+    /// notes without one are counted as Markdown. Encrypted notes are
+    /// decrypted for this and cached, notes which cannot be read are
+    /// skipped.
+    pub fn markup_distribution(&mut self) -> HashMap<MarkupLanguage, usize> {
+        let mut distribution: HashMap<MarkupLanguage, usize> = HashMap::new();
+        for note_id in self.ids_of_type(JoplinItemType::Note) {
+            let markup_language = match self.read_cached(&note_id) {
+                Ok(properties) => properties.markup_language().unwrap_or(MarkupLanguage::Markdown),
+                Err(_) => continue,
+            };
+            if self.notes[&note_id].may_be(JoplinItemType::Note) {
+                *distribution.entry(markup_language).or_insert(0) += 1;
+            }
+        }
+
+        distribution
+    }
+
+    /// Returns the ids of all notes whose property `key` (as serialized, see
+    /// [`JoplinNotebook::raw_properties`]) is exactly `value`, ordered by id.
+    /// Encrypted notes are decrypted for this and cached, the ones which
+    /// cannot be read are skipped.
+    pub fn find_by_property(&mut self, key: &str, value: &str) -> Vec<&str> {
+        let candidates: Vec<String> = self
+            .notes
+            .iter()
+            .filter(|(_, note)| note.may_be(JoplinItemType::Note))
+            .map(|(note_id, _)| note_id.to_string())
+            .collect();
+        let mut note_ids: Vec<String> = Vec::new();
+        for note_id in candidates {
+            if self.read_cached(&note_id).is_err() {
+                continue;
+            }
+            let note = &self.notes[&note_id];
+            if note.may_be(JoplinItemType::Note) && note.get_raw_property(key) == Some(value) {
+                note_ids.push(note_id);
+            }
+        }
+
+        let notes = &self.notes;
+        note_ids
+            .iter()
+            .filter_map(|note_id| notes.get_key_value(note_id.as_str()))
+            .map(|(note_id, _)| note_id.as_str())
+            .collect()
+    }
+
+    /// Returns the title of every tag together with the number of notes
+    /// tagged with it, ordered by title. Encrypted `Tag` and `NoteTag` items
+    /// are decrypted for this, which can be slow on the first call. The
+    /// content of the items is cached, so later calls are cheap. Items which
+    /// cannot be read are skipped.
+    pub fn all_tags(&mut self) -> Vec<(String, usize)> {
+        let mut titles: HashMap<String, String> = HashMap::new();
+        let mut usage: HashMap<String, usize> = HashMap::new();
+        let mut item_ids = self.ids_of_type(JoplinItemType::Tag);
+        item_ids.extend(self.ids_of_type(JoplinItemType::NoteTag));
+        for item_id in item_ids {
+            let item = &self.notes[&item_id];
+            let type_ = *item.get_type_();
+            if type_ == JoplinItemType::NoteTag && !item.is_encrypted() {
+                if let Some(tag_id) = item.get_tag_id() {
+                    *usage.entry(tag_id.to_string()).or_insert(0) += 1;
+                }
+                continue;
+            }
+            let properties = match self.read_cached(&item_id) {
+                Ok(properties) => properties,
+                Err(_) => continue,
+            };
+            if type_ == JoplinItemType::Tag {
+                if let Some(title) = properties.title() {
+                    titles.insert(item_id, title.to_string());
+                }
+            } else if let Some(tag_id) = properties.extra_properties().get("tag_id") {
+                *usage.entry(tag_id.to_string()).or_insert(0) += 1;
+            }
+        }
+
+        let mut tags: Vec<(String, usize)> = titles
+            .into_iter()
+            .map(|(tag_id, title)| {
+                let count = usage.get(&tag_id).copied().unwrap_or(0);
+                (title, count)
+            })
+            .collect();
+        tags.sort();
+
+        tags
+    }
+
+    /// Returns the ids of all notes tagged with `tag`, ordered by id. Tag
+    /// titles are matched case-insensitively. With `include_children` notes
+    /// tagged with a child tag (e.g. `project/alpha` for `project`) are
+    /// included as well. Encrypted items are decrypted anew for this, the
+    /// ones which cannot be read are skipped.
+    pub fn notes_with_tag(&self, tag: &str, include_children: bool) -> Vec<String> {
+        let tag = tag.to_lowercase();
+        let prefix = format!("{}/", tag);
+        let tag_ids: HashSet<String> = self
+            .tag_titles()
+            .into_iter()
+            .filter(|(_, title)| {
+                let title = title.to_lowercase();
+                title == tag || (include_children && title.starts_with(&prefix))
+            })
+            .map(|(tag_id, _)| tag_id)
+            .collect();
+
+        let mut note_ids: Vec<String> = self
+            .note_tags()
+            .into_iter()
+            .filter(|(_, tag_id)| tag_ids.contains(tag_id))
+            .map(|(note_id, _)| note_id)
+            .collect();
+        note_ids.sort_unstable();
+        note_ids.dedup();
+
+        note_ids
+    }
+
+    /// Groups the tags by their hierarchy, which Joplin encodes by separating
+    /// the levels of a title with `/`. Returns every tag (and every parent
+    /// implied by a title, even without a tag of its own) together with the
+    /// titles of its direct children, both ordered by title.
+    pub fn tag_hierarchy(&self) -> Vec<(String, Vec<String>)> {
+        let mut hierarchy: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (_, title) in self.tag_titles() {
+            let mut child = title.as_str();
+            hierarchy.entry(child.to_string()).or_default();
+            while let Some((parent, _)) = child.rsplit_once('/') {
+                let children = hierarchy.entry(parent.to_string()).or_default();
+                if !children.iter().any(|c| c == child) {
+                    children.push(child.to_string());
+                }
+                child = parent;
+            }
+        }
+
+        hierarchy
+            .into_iter()
+            .map(|(title, mut children)| {
+                children.sort();
+                (title, children)
+            })
+            .collect()
+    }
+
+    /// Returns the title of every `Tag` item by id. Encrypted ones are
+    /// decrypted, the ones which cannot be read are skipped.
+    fn tag_titles(&self) -> Vec<(String, String)> {
+        self.notes
+            .iter()
+            .filter(|(_, item)| item.may_be(JoplinItemType::Tag))
+            .filter_map(|(item_id, _)| {
+                let mut properties = self.item_properties(item_id)?;
+                if properties.get("type_").map(String::as_str) != Some("5") {
+                    return None;
+                }
+                Some((item_id.to_string(), properties.remove("title")?))
+            })
+            .collect()
+    }
+
+    /// Returns the ids of all to-dos, ordered by `todo_due` with the ones
+    /// without a due date last, and by id for the same due date. Completed
+    /// to-dos are only included with `include_completed`. All notes are
+    /// read (and decrypted) for this and cached, notes which cannot be read
+    /// are skipped.
+    pub fn todos(&mut self, include_completed: bool) -> Vec<&str> {
+        let mut todos: Vec<(Option<NaiveDateTime>, String)> = Vec::new();
+        for note_id in self.ids_of_type(JoplinItemType::Note) {
+            let properties = match self.read_cached(&note_id) {
+                Ok(properties) => properties,
+                Err(_) => continue,
+            };
+            let is_todo = properties.is_todo() == Some(true)
+                && (include_completed || properties.todo_completed().is_none());
+            let due = properties.todo_due().copied();
+            if is_todo && self.notes[&note_id].may_be(JoplinItemType::Note) {
+                todos.push((due, note_id));
+            }
+        }
+        todos.sort_by(|(a_due, a_id), (b_due, b_id)| match (a_due, b_due) {
+            (Some(a), Some(b)) => a.cmp(b).then(a_id.cmp(b_id)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => a_id.cmp(b_id),
+        });
+
+        let notes = &self.notes;
+        todos
+            .iter()
+            .filter_map(|(_, note_id)| notes.get_key_value(note_id.as_str()))
+            .map(|(note_id, _)| note_id.as_str())
+            .collect()
+    }
+
+    /// Returns the id of the note with the newest `user_updated_time` out of
+    /// `note_ids`, e.g. to pick the latest of several conflicting copies.
+    /// Ties are broken by picking the lexicographically larger id, notes
+    /// without a `user_updated_time` are older than all others. Fails if any
+    /// of the notes cannot be read, or with
+    /// [`JoplinReaderError::NoteIdNotFound`] if `note_ids` is empty.
+    pub fn latest_version(&mut self, note_ids: &[&str]) -> Result<&str, JoplinReaderError> {
+        let mut latest: Option<(Option<NaiveDateTime>, String)> = None;
+        for note_id in note_ids {
+            let note_id = self.find_note_id(note_id)?;
+            let properties = self.read_cached(&note_id)?;
+            let version = (properties.user_updated_time().copied(), note_id);
+            if latest.as_ref().is_none_or(|latest| version > *latest) {
+                latest = Some(version);
+            }
+        }
+
+        let latest = match latest {
+            Some((_, note_id)) => note_id,
+            None => {
+                return Err(JoplinReaderError::NoteIdNotFound {
+                    note_id: String::new(),
+                })
+            }
+        };
+        match self.notes.get_key_value(&latest) {
+            Some((note_id, _)) => Ok(note_id),
+            None => Err(JoplinReaderError::NoteIdNotFound {
+                note_id: latest.to_string(),
+            }),
+        }
+    }
+
+    /// Reads the body of every note. A note which cannot be read (e.g.
+    /// because its master key is missing) does not stop the others, it is
+    /// collected in [`ReadAllResult::failures`] instead.
+    pub fn read_all(&mut self) -> ReadAllResult {
+        let mut failures: Vec<(String, JoplinReaderError)> = Vec::new();
+        let notes = self.read_all_with(|note_id, e| failures.push((note_id.to_string(), e)));
+
+        ReadAllResult { notes, failures }
+    }
+
+    /// Same as [`JoplinNotebook::read_all`], but passes every failure to
+    /// `on_error` instead of collecting it.
+    pub fn read_all_with(
+        &mut self,
+        mut on_error: impl FnMut(&str, JoplinReaderError),
+    ) -> HashMap<String, String> {
+        let mut bodies: HashMap<String, String> = HashMap::new();
+        for note_id in self.ids_of_type(JoplinItemType::Note) {
+            match self.read_cached_body(&note_id) {
+                Ok(body) => {
+                    let body = body.to_string();
+                    bodies.insert(note_id, body);
+                }
+                Err(e) => on_error(&note_id, e),
+            }
+        }
+
+        bodies
+    }
+
+    /// Returns every note which links (`:/<id>`) to items that do not exist,
+    /// together with the missing ids, ordered by note id. Links to other
+    /// notes use the same syntax, so these are only broken if the note is
+    /// missing as well. Notes which cannot be read are skipped.
+    pub fn broken_resource_links(&mut self) -> Vec<(String, Vec<String>)> {
+        let bodies = self.read_all_with(|_, _| {});
+
+        let mut broken: Vec<(String, Vec<String>)> = Vec::new();
+        for (note_id, body) in bodies {
+            let mut missing: Vec<String> = Vec::new();
+            for captures in RESOURCE_LINK.captures_iter(&body) {
+                let resource_id = &captures[1];
+                if !self.notes.contains_key(resource_id) && !missing.iter().any(|m| m == resource_id) {
+                    missing.push(resource_id.to_string());
+                }
+            }
+            if !missing.is_empty() {
+                broken.push((note_id, missing));
+            }
+        }
+        broken.sort_unstable();
+
+        broken
+    }
+
+    /// Exports a note as a JSON object in the schema of the Joplin data API,
+    /// i.e. with all of its properties, timestamps in milliseconds since the
+    /// epoch and flags as numbers. Missing properties get the default the
+    /// API would return (empty, `0`). The note is decrypted if needed.
+    #[cfg(feature = "api-json")]
+    pub fn to_api_json(&mut self, note_id: &str) -> Result<String, JoplinReaderError> {
+        let properties = self.raw_properties(note_id)?;
+        // The outer type of encrypted items is not necessarily the real one
+        let found = match properties.get("type_").and_then(|t| t.trim().parse::<i32>().ok()) {
+            Some(t) => JoplinItemType::from(t),
+            None => *self.get_note(note_id)?.get_type_(),
+        };
+        if found != JoplinItemType::Note {
+            return Err(JoplinReaderError::WrongItemType {
+                expected: JoplinItemType::Note,
+                found,
+            });
+        }
+
+        let mut note = serde_json::Map::new();
+        for (name, type_) in API_NOTE_PROPERTIES {
+            let value = properties.get(*name).map(String::as_str).unwrap_or_default();
+            let value = match type_ {
+                ApiType::Text => serde_json::json!(value),
+                ApiType::Int => serde_json::json!(value.trim().parse::<i64>().unwrap_or(0)),
+                ApiType::Numeric => serde_json::json!(value.trim().parse::<f64>().unwrap_or(0.0)),
+                ApiType::Time => {
+                    let time = parse_time(value.trim()).map(|time| time.and_utc().timestamp_millis());
+                    serde_json::json!(time.unwrap_or(0))
+                }
+            };
+            note.insert(name.to_string(), value);
+        }
+
+        match serde_json::to_string(&note) {
+            Ok(json) => Ok(json),
+            Err(e) => Err(JoplinReaderError::InvalidFormat {
+                message: format!("Failed to serialize the note: {}", e),
+            }),
+        }
+    }
+
+    /// Exports the location of all notes as a GeoJSON `FeatureCollection`,
+    /// with the id and title of the note as properties of each feature.
+    /// Notes without coordinates are skipped, as are the ones at `0, 0`,
+    /// which is what Joplin stores when no location is known, and notes
+    /// which cannot be read.
+    #[cfg(feature = "geojson")]
+    pub fn export_geojson(&mut self) -> Result<String, JoplinReaderError> {
+        let mut features: Vec<serde_json::Value> = Vec::new();
+        for note_id in self.ids_of_type(JoplinItemType::Note) {
+            let properties = match self.read_cached(&note_id) {
+                Ok(properties) => properties,
+                Err(_) => continue,
+            };
+            let (latitude, longitude) = match (properties.latitude(), properties.longitude()) {
+                (Some(latitude), Some(longitude)) if latitude != 0.0 || longitude != 0.0 => {
+                    (latitude, longitude)
+                }
+                _ => continue,
+            };
+            let mut coordinates = vec![serde_json::json!(longitude), serde_json::json!(latitude)];
+            if let Some(altitude) = properties.altitude() {
+                coordinates.push(serde_json::json!(altitude));
+            }
+            let feature = serde_json::json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": coordinates,
+                },
+                "properties": {
+                    "id": note_id,
+                    "title": properties.title(),
+                },
+            });
+            features.push(feature);
+        }
+
+        let collection = serde_json::json!({
+            "type": "FeatureCollection",
+            "features": features,
+        });
+        match serde_json::to_string(&collection) {
+            Ok(geojson) => Ok(geojson),
+            Err(e) => Err(JoplinReaderError::InvalidFormat {
+                message: format!("Failed to serialize GeoJSON: {}", e),
+            }),
+        }
+    }
+
+    /// Iterates all notes and reads (and decrypts) each body only when the
+    /// iterator gets to it. Content which was not cached before is not kept
+    /// in the notebook afterwards, so even huge data folders can be
+    /// processed with bounded memory.
+    pub fn iter_decrypted(
+        &mut self,
+    ) -> impl Iterator<Item = (String, Result<String, JoplinReaderError>)> + '_ {
+        let (master_keys, store, options) = (&self.master_keys, &self.store, &self.options);
+        self.notes
+            .iter_mut()
+            .filter(|(_, note)| *note.get_type_() == JoplinItemType::Note)
+            .map(move |(note_id, note)| {
+                let was_cached = note.is_cached();
+                let body = JoplinNotebook::<S>::encryption_key_for(master_keys, note, options).and_then(
+                    |encryption_key| {
+                        note.read_with(store, encryption_key, options)
+                            .map(|body| body.to_string())
+                    },
+                );
+                if !was_cached {
+                    note.invalidate();
+                }
+                (note_id.to_string(), body)
+            })
+    }
+
+    /// Writes a decrypted backup of all notes and resources to `writer` as a
+    /// tar archive, with every note as `notes/<id>.md` (`# <title>` followed
+    /// by the body) and every resource file as `resources/<id>.<ext>`. The
+    /// entries are written one by one, the archive is never held in memory
+    /// as a whole. Items which cannot be read are skipped and returned
+    /// together with the reason, only failing to write aborts the export.
+    #[cfg(feature = "tar")]
+    pub fn export_to_tar<W: std::io::Write>(
+        &mut self,
+        writer: W,
+    ) -> Result<Vec<(String, JoplinReaderError)>, JoplinReaderError> {
+        let tar_error = |e: std::io::Error| JoplinReaderError::FileWriteError {
+            message: format!("Failed to write tar archive: {}", e),
+        };
+        let mut builder = tar::Builder::new(writer);
+        let mut failures: Vec<(String, JoplinReaderError)> = Vec::new();
+        let item_ids: Vec<String> = self.notes.keys().cloned().collect();
+        for item_id in item_ids {
+            let note = &self.notes[&item_id];
+            if !note.may_be(JoplinItemType::Note) && !note.may_be(JoplinItemType::Resource) {
+                continue;
+            }
+            let properties = match self.read_full(&item_id) {
+                Ok(properties) => properties.clone(),
+                Err(e) => {
+                    failures.push((item_id, e));
+                    continue;
+                }
+            };
+            let (path, content) = match self.notes[&item_id].get_type_() {
+                JoplinItemType::Note => {
+                    let body = properties.body().unwrap_or_default();
+                    let content = match properties.title() {
+                        Some(title) => format!("# {}\n\n{}", title, body),
+                        None => body.to_string(),
+                    };
+                    (format!("notes/{}.md", item_id), content.into_bytes())
+                }
+                JoplinItemType::Resource => match self.read_resource(&item_id) {
+                    Ok(content) => {
+                        let path = match properties.file_extension() {
+                            Some(extension) if !extension.is_empty() => {
+                                format!("resources/{}.{}", item_id, extension)
+                            }
+                            _ => format!("resources/{}", item_id),
+                        };
+                        (path, content)
+                    }
+                    Err(e) => {
+                        failures.push((item_id, e));
+                        continue;
+                    }
+                },
+                _ => continue,
+            };
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            if let Some(updated_time) = self.notes[&item_id].get_updated_time() {
+                header.set_mtime(updated_time.and_utc().timestamp().max(0) as u64);
+            }
+            header.set_cksum();
+            builder.append_data(&mut header, path, content.as_slice()).map_err(tar_error)?;
+        }
+        builder.finish().map_err(tar_error)?;
+
+        Ok(failures)
+    }
+
+    /// Exports the metadata of all items as CSV, one row per item ordered by
+    /// id, with the columns `id`, `title`, `type`, `parent_id`,
+    /// `created_time`, `updated_time`, `is_todo` and `is_encrypted`. Titles
+    /// are decrypted where needed, the columns taken from the content are
+    /// left empty for items which cannot be read.
+    #[cfg(feature = "csv")]
+    pub fn export_csv(&mut self) -> Result<String, JoplinReaderError> {
+        fn format_time(time: Option<&NaiveDateTime>) -> String {
+            time.map(|t| t.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string())
+                .unwrap_or_default()
+        }
+        let csv_error = |e: csv::Error| JoplinReaderError::InvalidFormat {
+            message: format!("Failed to write CSV: {}", e),
+        };
+
+        let mut rows: Vec<[String; 8]> = Vec::new();
+        let note_ids: Vec<String> = self.notes.keys().cloned().collect();
+        for note_id in note_ids {
+            let properties = self.read_cached(&note_id).ok().cloned().unwrap_or_default();
+            let note = &self.notes[&note_id];
+            rows.push([
+                note_id.to_string(),
+                properties.title().unwrap_or_default().to_string(),
+                format!("{:?}", note.get_type_()),
+                note.get_parent_id().unwrap_or_default().to_string(),
+                format_time(properties.created_time()),
+                format_time(note.get_updated_time()),
+                properties.is_todo().map(|t| t.to_string()).unwrap_or_default(),
+                note.is_encrypted().to_string(),
+            ]);
+        }
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer
+            .write_record([
+                "id",
+                "title",
+                "type",
+                "parent_id",
+                "created_time",
+                "updated_time",
+                "is_todo",
+                "is_encrypted",
+            ])
+            .map_err(csv_error)?;
+        for row in rows {
+            writer.write_record(&row).map_err(csv_error)?;
+        }
+        let data = writer.into_inner().map_err(|e| csv_error(e.into_error().into()))?;
+        match String::from_utf8(data) {
+            Ok(csv) => Ok(csv),
+            Err(_) => Err(JoplinReaderError::InvalidFormat {
+                message: "CSV is not valid UTF-8".to_string(),
+            }),
+        }
+    }
+
+    /// Checks every item of the notebook and reports all problems found.
+    /// All items are read (and decrypted) for this, nothing is cached.
+    pub fn health_check(&self) -> HealthReport {
+        let mut report = HealthReport {
+            missing_keys: self.undecryptable_notes().iter().map(|id| id.to_string()).collect(),
+            ..HealthReport::default()
+        };
+        for (path, error) in self.load_errors.iter() {
+            match error {
+                JoplinReaderError::DuplicateNoteId { .. } => report.duplicate_ids.push(path.to_string()),
+                _ => report.unparsable_files.push(path.to_string()),
+            }
+        }
+
+        let mut parents: Vec<(&str, String)> = Vec::new();
+        let mut folders: HashSet<&str> = HashSet::new();
+        for (note_id, note) in self.notes.iter() {
+            let encryption_key = match JoplinNotebook::<S>::encryption_key_for(&self.master_keys, note, &self.options) {
+                Ok(encryption_key) => encryption_key,
+                // Already reported as missing key
+                Err(_) => continue,
+            };
+            let properties = match note.read_raw_properties_with(&self.store, encryption_key, &self.options) {
+                Ok(properties) => properties,
+                Err(_) => {
+                    report.decryption_failures.push(note_id.to_string());
+                    continue;
+                }
+            };
+            let malformed = properties
+                .iter()
+                .any(|(k, v)| k.ends_with("_time") && !v.is_empty() && parse_time(v).is_none());
+            if malformed {
+                report.malformed_timestamps.push(note_id.to_string());
+            }
+            // The type of encrypted items is only known after decrypting them
+            let type_ = match properties.get("type_").and_then(|t| t.parse::<i32>().ok()) {
+                Some(t) => JoplinItemType::from(t),
+                None => *note.get_type_(),
+            };
+            if type_ == JoplinItemType::Folder {
+                folders.insert(note_id);
+            }
+            if type_ == JoplinItemType::Note || type_ == JoplinItemType::Folder {
+                if let Some(parent_id) = properties.get("parent_id").filter(|p| !p.is_empty()) {
+                    parents.push((note_id, parent_id.to_string()));
+                }
+            }
+        }
+        report.orphaned = parents
+            .into_iter()
+            .filter(|(_, parent_id)| !folders.contains(parent_id.as_str()))
+            .map(|(note_id, _)| note_id.to_string())
+            .collect();
+
+        report
+    }
+
+    /// Returns the title of every note, ordered by id. Notes which cannot be
+    /// read (e.g. because their master key is missing) have no title.
+    pub fn titles(&mut self) -> Vec<(String, Option<String>)> {
+        let mut titles: Vec<(String, Option<String>)> = Vec::new();
+        for note_id in self.ids_of_type(JoplinItemType::Note) {
+            let title = self
+                .read_cached(&note_id)
+                .map(|properties| properties.title().map(|title| title.to_string()));
+            if !self.notes[&note_id].may_be(JoplinItemType::Note) {
+                continue;
+            }
+            titles.push((note_id, title.ok().flatten()));
+        }
+
+        titles
+    }
+
+    /// Returns all notes whose title or body contains `query`, ignoring case.
+    /// Every note is read (and decrypted) for this, notes which cannot be
+    /// read are skipped.
+    pub fn search(&mut self, query: &str) -> Vec<SearchResult> {
+        let mut results: Vec<SearchResult> = Vec::new();
+        self.search_streaming(query, |result| {
+            results.push(result);
+            ControlFlow::Continue(())
+        });
+
+        results
+    }
+
+    /// Same as [`JoplinNotebook::search`], but passes every match to
+    /// `on_match` as soon as it is found. Returning [`ControlFlow::Break`]
+    /// stops the search, so the remaining notes are not decrypted. Notes are
+    /// searched in the order of their ids.
+    pub fn search_streaming(
+        &mut self,
+        query: &str,
+        mut on_match: impl FnMut(SearchResult) -> ControlFlow<()>,
+    ) {
+        let query = query.to_lowercase();
+        for note_id in self.ids_of_type(JoplinItemType::Note) {
+            let properties = match self.read_cached(&note_id) {
+                Ok(properties) => properties,
+                Err(_) => continue,
+            };
+            let matches = [properties.title(), properties.body()]
+                .iter()
+                .flatten()
+                .any(|text| text.to_lowercase().contains(&query));
+            if !matches {
+                continue;
+            }
+            let result = SearchResult {
+                title: properties.title().map(|title| title.to_string()),
+                note_id,
+            };
+            if on_match(result).is_break() {
+                break;
+            }
+        }
+    }
+
+    /// Counts the characters, words and lines of the body of a note. The
+    /// note is read (and decrypted) if it was not already.
+    pub fn note_stats(&mut self, note_id: &str) -> Result<NoteStats, JoplinReaderError> {
+        let body = self.read_note(note_id)?;
+
+        Ok(NoteStats::count(body))
+    }
+
+    /// Returns a hash of the content of a note, to check whether it changed
+    /// without comparing the whole note. Only the title and the body
+    /// contribute to it, so the hash does not change when e.g. only the
+    /// timestamps are updated by a sync, and is the same for the same
+    /// content in other notebooks. The hash is stable across versions of
+    /// this crate, it can be stored.
+    pub fn content_hash(&mut self, note_id: &str) -> Result<u64, JoplinReaderError> {
+        self.read_note(note_id)?;
+        let content = self.get_note(note_id)?.get_content();
+        let title = content.title().unwrap_or_default().as_bytes();
+        let body = content.body().unwrap_or_default().as_bytes();
+
+        // The length separates the title from the body
+        let hash = fnv1a(0xcbf2_9ce4_8422_2325, &(title.len() as u64).to_le_bytes());
+        let hash = fnv1a(hash, title);
+        Ok(fnv1a(hash, body))
+    }
+
+    /// Total number of words in the bodies of all notes. Notes which cannot
+    /// be read are skipped.
+    pub fn total_words(&mut self) -> usize {
+        let mut words: usize = 0;
+        for note_id in self.ids_of_type(JoplinItemType::Note) {
+            if let Ok(body) = self.read_cached_body(&note_id) {
+                words += NoteStats::count(body).words;
+            }
+        }
+
+        words
+    }
+
+    /// Returns the ids of all encrypted items whose master key was not
+    /// loaded. Only the header is checked, nothing is decrypted.
+    pub fn undecryptable_notes(&self) -> Vec<&str> {
+        self.notes
+            .iter()
+            .filter(|(_, note)| note.is_encrypted())
+            .filter(|(_, note)| match note.get_encryption_key_id() {
+                Some(key_id) => !self.master_keys.contains_key(key_id),
+                None => true,
+            })
+            .map(|(note_id, _)| note_id.as_str())
+            .collect()
+    }
+
+    /// Returns the id under which `note_id` is stored. Joplin ids are 32
+    /// lowercase hexadecimal characters, so ids in uppercase are found as
+    /// well, and anything else which is not stored fails with
+    /// [`JoplinReaderError::InvalidNoteId`] instead of
+    /// [`JoplinReaderError::NoteIdNotFound`].
+    fn find_note_id(&self, note_id: &str) -> Result<String, JoplinReaderError> {
+        if self.notes.contains_key(note_id) {
+            return Ok(note_id.to_string());
+        }
+        if !is_joplin_id(note_id) {
+            return Err(JoplinReaderError::InvalidNoteId {
+                id: note_id.to_string(),
+            });
+        }
+        let normalized = note_id.to_ascii_lowercase();
+        if self.notes.contains_key(&normalized) {
+            Ok(normalized)
+        } else {
+            Err(JoplinReaderError::NoteIdNotFound {
+                note_id: note_id.to_string(),
+            })
+        }
+    }
+
+    /// Returns the ids of all items encrypted with the master key `key_id`,
+    /// i.e. the ones which could no longer be read without it. Only the
+    /// headers are checked, nothing is decrypted.
+    pub fn notes_for_key(&self, key_id: &str) -> Vec<&str> {
+        self.notes
+            .iter()
+            .filter(|(_, note)| note.get_encryption_key_id() == Some(key_id))
+            .map(|(note_id, _)| note_id.as_str())
+            .collect()
+    }
+
+    /// Returns a [`NoteInfo`]
+    pub fn get_note(&self, note_id: &str) -> Result<&NoteInfo, JoplinReaderError> {
+        let note_id = self.find_note_id(note_id)?;
+        match self.notes.get(&note_id) {
+            Some(note) => Ok(note),
+            None => Err(JoplinReaderError::NoteIdNotFound {
+                note_id: note_id.to_string(),
+            }),
+        }
+    }
+
+    /// Returns the type of the item `note_id`, or `None` if there is no such
+    /// item. Only the already parsed header is used, nothing is decrypted.
+    pub fn item_type(&self, note_id: &str) -> Option<&JoplinItemType> {
+        let note_id = self.find_note_id(note_id).ok()?;
+        self.notes.get(&note_id).map(|note| note.get_type_())
+    }
+
+    /// Returns the ids of all notes in the folder `folder_id`. With
+    /// `recursive` the notes of all subfolders are included as well.
+    /// Only the parsed headers are used, so encrypted items without a
+    /// `parent_id` in their header are not found.
+    pub fn notes_under_folder(&self, folder_id: &str, recursive: bool) -> Vec<&str> {
+        let mut note_ids: Vec<&str> = Vec::new();
+        // Guards against parent loops in corrupt data folders
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut folders: Vec<&str> = vec![folder_id];
+        while let Some(folder) = folders.pop() {
+            if !visited.insert(folder) {
+                continue;
+            }
+            for (item_id, item) in self.notes.iter() {
+                if item.get_parent_id() != Some(folder) {
+                    continue;
+                }
+                match item.get_type_() {
+                    JoplinItemType::Note => note_ids.push(item_id),
+                    JoplinItemType::Folder if recursive => folders.push(item_id),
+                    _ => {}
+                }
+            }
+        }
+
+        note_ids
+    }
+
+    /// Returns the ids of all notes whose `parent_id` is not a loaded folder,
+    /// judging only by the item headers.
+    pub fn orphaned_notes(&self) -> Vec<&str> {
+        self.notes
+            .iter()
+            .filter(|(_, item)| *item.get_type_() == JoplinItemType::Note)
+            .filter(|(_, item)| match item.get_parent_id() {
+                Some(parent_id) if !parent_id.is_empty() => !matches!(
+                    self.notes.get(parent_id).map(|parent| parent.get_type_()),
+                    Some(JoplinItemType::Folder)
+                ),
+                _ => false,
+            })
+            .map(|(note_id, _)| note_id.as_str())
+            .collect()
+    }
+
+    /// Returns the ids of all notes which link to the resource `resource_id`,
+    /// according to the `NoteResource` items.
+    pub fn notes_using_resource(&self, resource_id: &str) -> Vec<&str> {
+        self.note_resources()
+            .filter(|(_, linked_resource)| *linked_resource == resource_id)
+            .map(|(note_id, _)| note_id)
+            .collect()
+    }
+
+    /// Returns the ids of all resources the note `note_id` links to,
+    /// according to the `NoteResource` items.
+    pub fn resources_in_note(&self, note_id: &str) -> Vec<&str> {
+        self.note_resources()
+            .filter(|(linked_note, _)| *linked_note == note_id)
+            .map(|(_, resource_id)| resource_id)
+            .collect()
+    }
+
+    /// Iterates the (note id, resource id) pairs of all `NoteResource` items.
+    fn note_resources(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.notes
+            .values()
+            .filter(|item| *item.get_type_() == JoplinItemType::NoteResource)
+            .filter_map(|item| match (item.get_note_id(), item.get_resource_id()) {
+                (Some(note_id), Some(resource_id)) => Some((note_id, resource_id)),
+                _ => None,
+            })
+    }
+
+    /// Returns all saved searches (`Search` and `SmartFilter` items), ordered
+    /// by id. They are read (and decrypted) anew, ones which cannot be read
+    /// are skipped.
+    pub fn saved_searches(&self) -> Vec<SavedSearch> {
+        let mut searches: Vec<SavedSearch> = Vec::new();
+        for (id, item) in self.notes.iter() {
+            let type_ = *item.get_type_();
+            if type_ != JoplinItemType::Search && type_ != JoplinItemType::SmartFilter {
+                continue;
+            }
+            let properties = JoplinNotebook::<S>::encryption_key_for(&self.master_keys, item, &self.options)
+                .and_then(|encryption_key| item.read_raw_properties_with(&self.store, encryption_key, &self.options));
+            let mut properties = match properties {
+                Ok(properties) => properties,
+                Err(_) => continue,
+            };
+            let query = match properties.remove("query") {
+                Some(query) => Some(query),
+                None => properties.remove("query_pattern"),
+            };
+            searches.push(SavedSearch {
+                id: id.to_string(),
+                type_,
+                title: properties.remove("title"),
+                query,
+            });
+        }
+
+        searches
+    }
+
+    /// Returns all reminders read from `Alarm` items, ordered by when they
+    /// are due. Alarms without a note or trigger time are left out.
+    pub fn alarms(&self) -> Vec<AlarmInfo> {
+        let mut alarms: Vec<AlarmInfo> = self
+            .notes
+            .values()
+            .filter(|item| *item.get_type_() == JoplinItemType::Alarm)
+            .filter_map(|item| match (item.get_note_id(), item.get_trigger_time()) {
+                (Some(note_id), Some(trigger_time)) => Some(AlarmInfo {
+                    note_id: note_id.to_string(),
+                    trigger_time: *trigger_time,
+                }),
+                _ => None,
+            })
+            .collect();
+        alarms.sort_by_key(|alarm| alarm.trigger_time);
+
+        alarms
+    }
+
+    /// Collects a [`NotebookSummary`]. Only the parsed headers are used,
+    /// nothing is decrypted.
+    pub fn summary(&self) -> NotebookSummary {
+        let mut summary = NotebookSummary {
+            total_items: self.notes.len(),
+            ..NotebookSummary::default()
+        };
+        let mut key_ids: HashSet<&str> = HashSet::new();
+        for note in self.notes.values() {
+            *summary.items_per_type.entry(*note.get_type_()).or_insert(0) += 1;
+            if note.is_encrypted() {
+                summary.encrypted_items += 1;
+            }
+            if let Some(key_id) = note.get_encryption_key_id() {
+                key_ids.insert(key_id);
+            }
+            if let Some(&updated_time) = note.get_updated_time() {
+                summary.oldest_updated_time =
+                    Some(summary.oldest_updated_time.map_or(updated_time, |t| t.min(updated_time)));
+                summary.newest_updated_time =
+                    Some(summary.newest_updated_time.map_or(updated_time, |t| t.max(updated_time)));
+            }
+        }
+        summary.master_keys_referenced = key_ids.len();
+
+        summary
+    }
+
+    /// Returns the ids of all items whose `updated_time` is newer than
+    /// `since`. Only the parsed headers are used, nothing is decrypted.
+    pub fn notes_modified_since(&self, since: NaiveDateTime) -> Vec<&str> {
+        self.notes
+            .iter()
+            .filter(|(_, note)| note.get_updated_time().is_some_and(|t| *t > since))
+            .map(|(note_id, _)| note_id.as_str())
+            .collect()
+    }
+
+    /// Whether the items are only parsed when first accessed. Notebooks are
+    /// currently always loaded eagerly, i.e. all headers are parsed in
+    /// [`JoplinNotebook::new`], so this is always `false`.
+    pub fn is_lazy(&self) -> bool {
+        false
+    }
+
+    /// Whether the content of `note_id` has been read and is cached. The
+    /// headers of all items are always parsed while loading (see
+    /// [`JoplinNotebook::is_lazy`]), only the content is read on demand.
+    pub fn is_note_loaded(&self, note_id: &str) -> bool {
+        self.notes.get(note_id).is_some_and(|note| note.is_cached())
+    }
+
+    /// Returns all items ordered by id.
+    pub fn notes_ordered(&self) -> Vec<&NoteInfo> {
+        self.notes.values().collect()
+    }
+
+    /// Returns all items for which `pred` holds, ordered by id. Only the
+    /// parsed headers are available, nothing is read or decrypted.
+    pub fn filter_notes(&self, pred: impl Fn(&NoteInfo) -> bool) -> Vec<&NoteInfo> {
+        self.notes.values().filter(|note| pred(note)).collect()
+    }
+
+    /// Iterate all item Ids stored, ordered by id
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.notes.keys()
+    }
+
+    /// Starts a [`NoteQuery`] over all items.
+    pub fn query(&self) -> NoteQuery<'_, S> {
+        NoteQuery {
+            notebook: self,
+            type_: None,
+            updated_after: None,
+            tags: Vec::new(),
+            predicates: Vec::new(),
+        }
+    }
+}
+
+/// Chainable filter over the items of a [`JoplinNotebook`], see
+/// [`JoplinNotebook::query`]. All conditions have to hold, nothing is
+/// evaluated before [`NoteQuery::execute`].
+/// ```rust,no_run
+/// use joplin_reader::{JoplinItemType, JoplinNotebook};
+///
+/// let notebook = JoplinNotebook::new("./Joplin", vec![])?;
+/// let work_notes = notebook.query().of_type(JoplinItemType::Note).with_tag("work").execute();
+/// # Ok::<(), joplin_reader::JoplinReaderError>(())
+/// ```
+pub struct NoteQuery<'a, S: NoteStore = FsStore> {
+    notebook: &'a JoplinNotebook<S>,
+    type_: Option<JoplinItemType>,
+    updated_after: Option<NaiveDateTime>,
+    tags: Vec<String>,
+    predicates: Vec<NotePredicate<'a>>,
+}
+
+/// Custom condition of a [`NoteQuery`]
+type NotePredicate<'a> = Box<dyn Fn(&NoteInfo) -> bool + 'a>;
+
+impl<'a, S: NoteStore> NoteQuery<'a, S> {
+    /// Only items of type `type_`, judging by the parsed header.
+    pub fn of_type(mut self, type_: JoplinItemType) -> Self {
+        self.type_ = Some(type_);
+        self
+    }
+
+    /// Only items whose `updated_time` is newer than `since`.
+    pub fn updated_after(mut self, since: NaiveDateTime) -> Self {
+        self.updated_after = Some(since);
+        self
+    }
+
+    /// Only notes tagged with `tag`, see [`JoplinNotebook::notes_with_tag`].
+    /// Tags are only read (and decrypted) on [`NoteQuery::execute`].
+    pub fn with_tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    /// Only items for which `pred` holds, see
+    /// [`JoplinNotebook::filter_notes`].
+    pub fn filter(mut self, pred: impl Fn(&NoteInfo) -> bool + 'a) -> Self {
+        self.predicates.push(Box::new(pred));
+        self
+    }
+
+    /// Returns all items matching the query, ordered by id.
+    pub fn execute(self) -> Vec<&'a NoteInfo> {
+        let tagged: Vec<HashSet<String>> = self
+            .tags
+            .iter()
+            .map(|tag| self.notebook.notes_with_tag(tag, false).into_iter().collect())
+            .collect();
+
+        self.notebook
+            .notes
+            .iter()
+            .filter(|(_, note)| self.type_.is_none_or(|type_| *note.get_type_() == type_))
+            .filter(|(_, note)| {
+                self.updated_after
+                    .is_none_or(|since| note.get_updated_time().is_some_and(|t| *t > since))
+            })
+            .filter(|(note_id, _)| tagged.iter().all(|note_ids| note_ids.contains(*note_id)))
+            .filter(|(_, note)| self.predicates.iter().all(|pred| pred(note)))
+            .map(|(_, note)| note)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::note::JoplinItemType;
+    use crate::store::MemoryStore;
+
+    const MASTER_KEY_ID: &str = "3336eb7a2472d9ae4a690a978fa8a46f";
+    const PASSWORD: &str = "3336eb7a2472d9ae4a690a978fa8a46f,plaintext_password";
+    const MASTER_KEY: &str = r#"id: 3336eb7a2472d9ae4a690a978fa8a46f
+content: {"iv":"CeNPVkXTm0LMvPSo6xzzew==","v":1,"iter":1000,"ks":128,"ts":64,"mode":"ccm","adata":"","cipher":"aes","salt":"Lm5+aHzj/XM=","ct":"i1/x0WOQe1sPIBw0QYQSO7VezNEfHpNMpw+L/do7PFvqCwK2sWYS1Wm5lIHAaGm0KcUC1Kd/45qVWO8IOx+QdFWirx65Y8BM"}
+type_: 9"#;
+    const ENCRYPTED_FOLDER: &str = r#"id: 6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c
+encryption_cipher_text: JED01000022053336eb7a2472d9ae4a690a978fa8a46f0000e9{"iv":"YjTWP05NwoStCiBgiffE9Q==","v":1,"iter":1000,"ks":128,"ts":64,"mode":"ccm","adata":"","cipher":"aes","salt":"TznhuWAEAnE=","ct":"7MJucCYwkqQ3NSpebchdtfHFr4Fge0rwaHxYjT8lG8PlkEJZFjbV+zXVAFfCmYWgaHwMez4400V11/KX6UHIhR1IKmiVkh0="}
+encryption_applied: 1
+updated_time: 2021-03-20T10:00:00.000Z
+type_: 1"#;
+    // Note with the title `Percent`, not part of `encrypted_store`
+    const ENCRYPTED_NOTE: &str = r#"id: 1b2c3d4e5f60718293a4b5c6d7e8f901
+encryption_cipher_text: JED01000022053336eb7a2472d9ae4a690a978fa8a46f0000f5{"iv":"YjTWP05NwoStCiBgiffE9Q==","v":1,"iter":1000,"ks":128,"ts":64,"mode":"ccm","adata":"","cipher":"aes","salt":"TznhuWAEAnE=","ct":"68hueElUj8oHQG9df49X4fSXtoM8bF7XVxcGiz1mE8XnlkBbEDLT/zeGPRab2dn9fRcHKmcuvQZpn/Hx41Dw98pUvxHYqsWNKYDIwZpF3A=="}
+encryption_applied: 1
+updated_time: 2021-03-20T10:00:00.000Z
+type_: 1"#;
+
+    fn encrypted_store() -> MemoryStore {
+        let mut store = MemoryStore::new();
+        store.insert(&format!("{}.md", MASTER_KEY_ID), MASTER_KEY);
+        store.insert("6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c.md", ENCRYPTED_FOLDER);
+        store
+    }
+
+    #[test]
+    fn encrypted_item_type_is_taken_from_decrypted_properties() {
+        let mut notebook = JoplinNotebook::with_store(encrypted_store(), vec![PASSWORD]).unwrap();
+        let folder_id = "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c";
+        assert_eq!(*notebook.get_note(folder_id).unwrap().get_type_(), JoplinItemType::Note);
+        assert_eq!(notebook.get_note(folder_id).unwrap().effective_type(), None);
+
+        // Folders have no body
+        match notebook.read_note(folder_id) {
+            Err(JoplinReaderError::WrongItemType { expected, found }) => {
+                assert_eq!(expected, JoplinItemType::Note);
+                assert_eq!(found, JoplinItemType::Folder);
+            }
+            other => panic!("Expected a folder not to be a note, got {:?}", other),
+        }
+        let folder = notebook.get_note(folder_id).unwrap();
+        assert_eq!(*folder.get_type_(), JoplinItemType::Folder);
+        assert_eq!(folder.effective_type(), Some(JoplinItemType::Folder));
+        let raw_properties = notebook.raw_properties(folder_id).unwrap();
+        assert_eq!(raw_properties.get("title").map(String::as_str), Some("Work"));
+        assert_eq!(raw_properties.get("type_").map(String::as_str), Some("2"));
+        assert_eq!(notebook.item_type(folder_id), Some(&JoplinItemType::Folder));
+        assert_eq!(notebook.item_type("missing"), None);
+    }
+
+    #[test]
+    fn read_note_with_key_does_not_cache_the_content() {
+        let note_id = "1b2c3d4e5f60718293a4b5c6d7e8f901";
+        let folder_id = "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c";
+        let key = "f4b7c3a9e1d2086b5a7c9e3f1d2b4a6c8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b";
+        let mut store = encrypted_store();
+        store.insert(&format!("{}.md", note_id), ENCRYPTED_NOTE);
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(notebook.read_note_with_key(note_id, key).unwrap(), "Use A for A");
+        assert!(!notebook.get_note(note_id).unwrap().is_cached());
+        assert!(matches!(notebook.read_note(note_id), Err(JoplinReaderError::NoEncryptionKey { .. })));
+        assert!(matches!(
+            notebook.read_note_with_key(folder_id, key),
+            Err(JoplinReaderError::WrongItemType {
+                found: JoplinItemType::Folder,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn notes_are_loaded_once_their_content_was_read() {
+        let note_id = "1b2c3d4e5f60718293a4b5c6d7e8f901";
+        let mut store = encrypted_store();
+        store.insert(&format!("{}.md", note_id), ENCRYPTED_NOTE);
+        let mut notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+
+        assert!(!notebook.is_lazy());
+        assert!(!notebook.is_note_loaded(note_id));
+        assert_eq!(notebook.read_note(note_id).unwrap(), "Use A for A");
+        assert!(notebook.is_note_loaded(note_id));
+        assert!(!notebook.is_note_loaded("missing"));
+    }
+
+    #[test]
+    fn single_notes_are_read_by_id_only() {
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        let broken_id = "d".repeat(32);
+        let folder_id = "b".repeat(32);
+        let mut store = MemoryStore::new();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!("Title\n\nBody\n\nid: {}\ntype_: 1\nencryption_applied: 0", note_id),
+        );
+        store.insert(&format!("{}.md", folder_id), item(&folder_id, "", 2));
+        store.insert("secret.md", "Secret\n\nid: secret\ntype_: 1\nencryption_applied: 0");
+        store.insert(
+            &format!("{}.md", broken_id),
+            format!("Title\n\nid: {}\ntype_: x\nencryption_applied: 0", broken_id),
+        );
+
+        let read = |note_id: &str| JoplinNotebook::read_single_note_from_store(&store, note_id, vec![]);
+        assert_eq!(read(&note_id.to_uppercase()).unwrap(), "Body");
+        assert!(matches!(read("secret"), Err(JoplinReaderError::InvalidNoteId { .. })));
+        assert!(matches!(read("../../etc/passwd"), Err(JoplinReaderError::InvalidNoteId { .. })));
+        assert!(matches!(read(&"c".repeat(32)), Err(JoplinReaderError::NoteIdNotFound { .. })));
+        assert!(matches!(read(&broken_id), Err(JoplinReaderError::FileReadError { .. })));
+        assert!(matches!(
+            read(&folder_id),
+            Err(JoplinReaderError::WrongItemType {
+                found: JoplinItemType::Folder,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn decryption_stops_at_max_note_bytes() {
+        let mut notebook = JoplinNotebook::builder()
+            .max_note_bytes(16)
+            .build_with_store(encrypted_store(), vec![PASSWORD])
+            .unwrap();
+        match notebook.read_note("6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c") {
+            Err(JoplinReaderError::NoteTooLarge { limit }) => assert_eq!(limit, 16),
+            _ => panic!("Expected the note to exceed the size limit"),
+        }
+    }
+
+    #[test]
+    fn decryption_stops_at_max_note_chunks() {
+        let notebook = JoplinNotebook::builder()
+            .max_note_chunks(0)
+            .build_with_store(encrypted_store(), vec![PASSWORD])
+            .unwrap();
+        match notebook.note_decrypt_stats("6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c") {
+            Err(JoplinReaderError::TooManyChunks { limit }) => assert_eq!(limit, 0),
+            _ => panic!("Expected the note to exceed the chunk limit"),
+        }
+    }
+
+    fn item(id: &str, parent_id: &str, type_: u8) -> String {
+        format!(
+            "Title\n\nid: {}\nparent_id: {}\ntype_: {}\nencryption_applied: 0",
+            id, parent_id, type_
+        )
+    }
+
+    #[test]
+    fn notes_under_folder_follows_subfolders() {
+        let mut store = MemoryStore::new();
+        store.insert("root.md", item("root", "", 2));
+        store.insert("sub.md", item("sub", "root", 2));
+        store.insert("a.md", item("a", "root", 1));
+        store.insert("b.md", item("b", "sub", 1));
+        // A parent loop must not hang
+        store.insert("loop1.md", item("loop1", "loop2", 2));
+        store.insert("loop2.md", item("loop2", "loop1", 2));
+        store.insert("c.md", item("c", "loop2", 1));
+        let notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(notebook.notes_under_folder("root", false), vec!["a"]);
+        let mut notes = notebook.notes_under_folder("root", true);
+        notes.sort_unstable();
+        assert_eq!(notes, vec!["a", "b"]);
+        assert_eq!(notebook.notes_under_folder("loop1", true), vec!["c"]);
+    }
+
+    #[test]
+    fn debug_output_redacts_master_keys() {
+        let notebook = JoplinNotebook::with_store(encrypted_store(), vec![PASSWORD]).unwrap();
+        let master_key = notebook.master_keys.get(MASTER_KEY_ID).unwrap();
+        let output = format!("{:?}", notebook);
+        assert!(output.contains(MASTER_KEY_ID));
+        assert!(output.contains("<redacted>"));
+        assert!(!output.contains(master_key.as_str()));
+    }
+
+    #[test]
+    fn note_resources_link_notes_and_resources() {
+        let mut store = MemoryStore::new();
+        for (id, note_id, resource_id) in [
+            ("link1", "note1", "res1"),
+            ("link2", "note1", "res2"),
+            ("link3", "note2", "res1"),
+        ] {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "id: {}\nnote_id: {}\nresource_id: {}\ntype_: 11\nencryption_applied: 0",
+                    id, note_id, resource_id
+                ),
+            );
+        }
+        let notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        let mut notes = notebook.notes_using_resource("res1");
+        notes.sort_unstable();
+        assert_eq!(notes, vec!["note1", "note2"]);
+        let mut resources = notebook.resources_in_note("note1");
+        resources.sort_unstable();
+        assert_eq!(resources, vec!["res1", "res2"]);
+        assert!(notebook.resources_in_note("missing").is_empty());
+    }
+
+    #[test]
+    fn alarms_are_ordered_by_trigger_time() {
+        let mut store = MemoryStore::new();
+        store.insert(
+            "alarm1.md",
+            "id: alarm1\nnote_id: note1\ntrigger_time: 2021-03-21T08:00:00.000Z\ntype_: 8\nencryption_applied: 0",
+        );
+        store.insert(
+            "alarm2.md",
+            "id: alarm2\nnote_id: note2\ntrigger_time: 1616227200000\ntype_: 8\nencryption_applied: 0",
+        );
+        let notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        let alarms = notebook.alarms();
+        assert_eq!(alarms.len(), 2);
+        assert_eq!(alarms[0].note_id, "note2");
+        assert_eq!(alarms[0].trigger_time.to_string(), "2021-03-20 08:00:00");
+        assert_eq!(alarms[1].note_id, "note1");
+    }
+
+    #[test]
+    fn summary_counts_headers() {
+        let mut store = encrypted_store();
+        store.insert(
+            "note.md",
+            "Title\n\nid: note\ntype_: 1\nencryption_applied: 0\nupdated_time: 2020-01-01T00:00:00.000Z",
+        );
+        let notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+
+        let summary = notebook.summary();
+        assert_eq!(summary.total_items, 2);
+        assert_eq!(summary.items_per_type.get(&JoplinItemType::Note), Some(&2));
+        assert_eq!(summary.encrypted_items, 1);
+        assert_eq!(summary.master_keys_referenced, 1);
+        assert_eq!(summary.oldest_updated_time.unwrap().to_string(), "2020-01-01 00:00:00");
+        assert!(summary.newest_updated_time > summary.oldest_updated_time);
+    }
+
+    #[test]
+    fn all_tags_counts_tagged_notes() {
+        let mut store = MemoryStore::new();
+        store.insert("tag1.md", "work\n\nid: tag1\ntype_: 5\nencryption_applied: 0");
+        store.insert("tag2.md", "home\n\nid: tag2\ntype_: 5\nencryption_applied: 0");
+        for (id, note_id) in [("link1", "note1"), ("link2", "note2")] {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "id: {}\nnote_id: {}\ntag_id: tag1\ntype_: 6\nencryption_applied: 0",
+                    id, note_id
+                ),
+            );
+        }
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(
+            notebook.all_tags(),
+            vec![("home".to_string(), 0), ("work".to_string(), 2)]
+        );
+    }
+
+    #[test]
+    fn known_non_item_files_are_ignored() {
+        assert!(is_ignored("database.sqlite", &default_ignored_files()));
+        assert!(is_ignored("lock/1_2_abc.json", &default_ignored_files()));
+        assert!(is_ignored("info.json", &default_ignored_files()));
+        assert!(!is_ignored("9a20a9e4d336de70cb6d22a58a3e673c.md", &default_ignored_files()));
+
+        let mut store = MemoryStore::new();
+        let item = "Title\n\nid: custom\ntype_: 1\nencryption_applied: 0";
+        store.insert("custom.md", item);
+        store.insert("backup.md", item.replace("custom", "backup"));
+        let notebook = JoplinNotebook::builder()
+            .ignore_file("backup.md")
+            .build_with_store(store, vec![])
+            .unwrap();
+        assert!(notebook.get_note("custom").is_ok());
+        assert!(notebook.get_note("backup").is_err());
+    }
+
+    #[test]
+    fn latest_version_picks_newest_user_updated_time() {
+        let mut store = MemoryStore::new();
+        for (id, time) in [
+            ("a", "2021-01-01T00:00:00.000Z"),
+            ("b", "2021-02-01T00:00:00.000Z"),
+            ("c", "2021-02-01T00:00:00.000Z"),
+            ("d", ""),
+            ("9a20a9e4d336de70cb6d22a58a3e673c", "2021-03-01T00:00:00.000Z"),
+        ] {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "Title\n\nBody\n\nid: {}\nuser_updated_time: {}\ntype_: 1\nencryption_applied: 0",
+                    id, time
+                ),
+            );
+        }
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(notebook.latest_version(&["a", "d"]).unwrap(), "a");
+        assert_eq!(notebook.latest_version(&["b", "a", "c"]).unwrap(), "c");
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        assert_eq!(notebook.latest_version(&[&note_id.to_uppercase(), "d"]).unwrap(), note_id);
+        assert_eq!(notebook.item_type(&note_id.to_uppercase()), Some(&JoplinItemType::Note));
+        assert!(notebook.latest_version(&["a", "missing"]).is_err());
+        assert!(notebook.latest_version(&[]).is_err());
+    }
+
+    #[test]
+    fn read_all_collects_failures() {
+        let mut store = encrypted_store();
+        store.insert("note.md", "Title\n\nBody\n\nid: note\ntype_: 1\nencryption_applied: 0");
+        // Loaded without the password, so the encrypted item cannot be read
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+        assert!(notebook.get_note(MASTER_KEY_ID).is_err());
+
+        let result = notebook.read_all();
+        assert_eq!(result.notes.get("note").map(String::as_str), Some("Body"));
+        assert_eq!(result.failures.len(), 1);
+        match &result.failures[0] {
+            (note_id, JoplinReaderError::NoEncryptionKey { .. }) => {
+                assert_eq!(note_id, "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c")
+            }
+            failure => panic!("Unexpected failure {:?}", failure),
+        }
+    }
+
+    #[cfg(feature = "geojson")]
+    #[test]
+    fn export_geojson_contains_notes_with_coordinates() {
+        let mut store = MemoryStore::new();
+        store.insert(
+            "here.md",
+            "Here\n\nBody\n\nid: here\nlatitude: 48.13743000\nlongitude: 11.57549000\naltitude: 0.0000\ntype_: 1\nencryption_applied: 0",
+        );
+        store.insert(
+            "nowhere.md",
+            "Nowhere\n\nBody\n\nid: nowhere\nlatitude: 0.00000000\nlongitude: 0.00000000\ntype_: 1\nencryption_applied: 0",
+        );
+        store.insert("locked.md", ENCRYPTED_NOTE);
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        let geojson: serde_json::Value =
+            serde_json::from_str(&notebook.export_geojson().unwrap()).unwrap();
+        assert_eq!(geojson["type"], "FeatureCollection");
+        let features = geojson["features"].as_array().unwrap();
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0]["properties"]["id"], "here");
+        assert_eq!(features[0]["properties"]["title"], "Here");
+        assert_eq!(features[0]["geometry"]["coordinates"][0], 11.57549);
+        assert_eq!(features[0]["geometry"]["coordinates"][1], 48.13743);
+    }
+
+    #[test]
+    fn iter_decrypted_yields_every_note() {
+        let mut store = encrypted_store();
+        store.insert("note.md", "Title\n\nBody\n\nid: note\ntype_: 1\nencryption_applied: 0");
+        store.insert("folder.md", "Folder\n\nid: folder\ntype_: 2\nencryption_applied: 0");
+        let mut notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+        notebook.read_note("note").unwrap();
+
+        let mut bodies: Vec<(String, Result<String, JoplinReaderError>)> =
+            notebook.iter_decrypted().collect();
+        bodies.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(bodies.len(), 2);
+        // The encrypted item turns out to be a folder, which has no body
+        assert!(bodies[0].1.is_err());
+        assert_eq!(bodies[1].0, "note");
+        assert_eq!(bodies[1].1.as_deref().unwrap(), "Body");
+        // Only the content read for the iteration is dropped again
+        assert!(notebook.get_note("note").unwrap().is_cached());
+        assert!(!notebook.get_note("6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c").unwrap().is_cached());
+    }
+
+    #[test]
+    fn notes_modified_since_compares_updated_time() {
+        let mut store = encrypted_store();
+        store.insert(
+            "old.md",
+            "Title\n\nid: old\ntype_: 1\nencryption_applied: 0\nupdated_time: 2020-01-01T00:00:00.000Z",
+        );
+        let notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+
+        let since = NaiveDateTime::parse_from_str("2021-01-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(notebook.notes_modified_since(since), vec!["6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c"]);
+    }
+
+    #[test]
+    fn add_raw_key_decrypts_without_passphrase() {
+        let mut notebook = JoplinNotebook::with_store(encrypted_store(), vec![]).unwrap();
+        let folder_id = "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c";
+        assert!(notebook.note_decrypt_stats(folder_id).is_err());
+
+        let key = hex::decode("f4b7c3a9e1d2086b5a7c9e3f1d2b4a6c8e0f1a3b5c7d9e2f4a6b8c0d1e3f5a7b").unwrap();
+        notebook.add_raw_key(MASTER_KEY_ID, key);
+        assert_eq!(notebook.note_decrypt_stats(folder_id).unwrap().chunks, 1);
+        assert!(notebook.undecryptable_notes().is_empty());
+        assert_eq!(notebook.notes_for_key(MASTER_KEY_ID), vec![folder_id]);
+        assert!(notebook.notes_for_key("00000000000000000000000000000000").is_empty());
+    }
+
+    #[test]
+    fn note_ids_are_validated_and_normalized() {
+        let mut notebook = JoplinNotebook::with_store(encrypted_store(), vec![PASSWORD]).unwrap();
+        assert!(notebook.get_note("6F2B8A1C9D3E4F5A7B8C9D0E1F2A3B4C").is_ok());
+        match notebook.read_note("My note title") {
+            Err(JoplinReaderError::InvalidNoteId { id }) => assert_eq!(id, "My note title"),
+            _ => panic!("Expected an invalid note id"),
+        }
+        match notebook.get_note("00000000000000000000000000000000") {
+            Err(JoplinReaderError::NoteIdNotFound { .. }) => {}
+            _ => panic!("Expected the note not to be found"),
+        }
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn export_csv_escapes_titles() {
+        let mut store = encrypted_store();
+        store.insert(
+            "note.md",
+            "Title, with \"quotes\"\n\nBody\n\nid: note\nparent_id: folder\nis_todo: 1\ncreated_time: 2020-01-01T00:00:00.000Z\ntype_: 1\nencryption_applied: 0",
+        );
+        let mut notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+
+        let csv = notebook.export_csv().unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "id,title,type,parent_id,created_time,updated_time,is_todo,is_encrypted",
+                "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c,Work,Folder,,,2021-03-20T10:00:00.000Z,,true",
+                "note,\"Title, with \"\"quotes\"\"\",Note,folder,2020-01-01T00:00:00.000Z,,true,false",
+            ]
+        );
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn markdown_events_rewrite_joplin_links() {
+        use pulldown_cmark::{Event, Tag};
+
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        let image_id = "0123456789abcdef0123456789abcdef";
+        let mut store = MemoryStore::new();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!(
+                "Title\n\n# Heading\n\n![image](:/{}) [web](https://joplinapp.org)\n\nid: {}\ntype_: 1\nencryption_applied: 0",
+                image_id, note_id
+            ),
+        );
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        let events = notebook.note_markdown_events(note_id).unwrap();
+        assert!(events.contains(&Event::Text("Heading".into())));
+        let urls: Vec<String> = events
+            .iter()
+            .filter_map(|event| match event {
+                Event::Start(Tag::Link { dest_url, .. }) | Event::Start(Tag::Image { dest_url, .. }) => {
+                    Some(dest_url.to_string())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            urls,
+            vec![format!("joplin://{}", image_id), "https://joplinapp.org".to_string()]
+        );
+    }
+
+    #[test]
+    fn resources_are_read_and_decrypted() {
+        let image_id = "0123456789abcdef0123456789abcdef";
+        let crypted_id = "fedcba9876543210fedcba9876543210";
+        let mut store = encrypted_store();
+        let resource = |id: &str, extra: &str| {
+            format!("image.png\n\nid: {}\nmime: image/png\nfile_extension: png\n{}type_: 4\nencryption_applied: 0", id, extra)
+        };
+        store.insert(&format!("{}.md", image_id), resource(image_id, ""));
+        store.insert(&format!("resources/{}.png", image_id), vec![0x89, b'P', b'N', b'G']);
+        store.insert(&format!("{}.md", crypted_id), resource(crypted_id, "encryption_blob_encrypted: 1\n"));
+        store.insert(
+            &format!(".resource/{}", crypted_id),
+            r#"JED01000022053336eb7a2472d9ae4a690a978fa8a46f000099{"iv":"YjTWP05NwoStCiBgiffE9Q==","v":1,"iter":1000,"ks":128,"ts":64,"mode":"ccm","adata":"","cipher":"aes","salt":"TznhuWAEAnE=","ct":"0vteVPscnno/carO"}000099{"iv":"5meSIpyLG5BYJ6VXKuLLPg==","v":1,"iter":1000,"ks":128,"ts":64,"mode":"ccm","adata":"","cipher":"aes","salt":"1Oeswbcmoxs=","ct":"kRoDn3FjVJbWr8RA"}"#,
+        );
+        let mut notebook = JoplinNotebook::with_store(store.clone(), vec![PASSWORD]).unwrap();
+
+        assert_eq!(notebook.read_resource(image_id).unwrap(), vec![0x89, b'P', b'N', b'G']);
+        assert_eq!(notebook.read_resource(crypted_id).unwrap(), vec![0x89, b'P', b'N', b'G']);
+
+        // The limits of the notebook apply to resources as well
+        let mut notebook = JoplinNotebook::builder()
+            .max_note_chunks(1)
+            .build_with_store(store, vec![PASSWORD])
+            .unwrap();
+        assert!(matches!(
+            notebook.read_resource(crypted_id),
+            Err(JoplinReaderError::TooManyChunks { limit: 1 })
+        ));
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn resources_are_inlined_into_html() {
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        let image_id = "0123456789abcdef0123456789abcdef";
+        let pdf_id = "fedcba9876543210fedcba9876543210";
+        let evil_id = "e".repeat(32);
+        let mut store = MemoryStore::new();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!(
+                "Title\n\n![image](:/{}) [file](:/{}) [web](https://joplinapp.org) [evil](:/{})\n\nid: {}\ntype_: 1\nencryption_applied: 0",
+                image_id, pdf_id, evil_id, note_id
+            ),
+        );
+        store.insert(
+            &format!("{}.md", image_id),
+            format!("image.png\n\nid: {}\nmime: image/png\ntype_: 4\nencryption_applied: 0", image_id),
+        );
+        store.insert(&format!(".resource/{}", image_id), vec![0x89, b'P', b'N', b'G']);
+        store.insert(
+            &format!("{}.md", pdf_id),
+            format!(
+                "Report\n\nid: {}\nmime: application/pdf\nfilename: \"report\".pdf\ntype_: 4\nencryption_applied: 0",
+                pdf_id
+            ),
+        );
+        store.insert(&format!(".resource/{}", pdf_id), "%PDF");
+        store.insert(
+            &format!("{}.md", evil_id),
+            format!(
+                "Evil\n\nid: {}\nmime: text/html\"><script>alert(1)</script>\ntype_: 4\nencryption_applied: 0",
+                evil_id
+            ),
+        );
+        store.insert(&format!(".resource/{}", evil_id), "%PDF");
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(
+            notebook.read_note_html_inline(note_id).unwrap(),
+            "<p><img src=\"data:image/png;base64,iVBORw==\" alt=\"image\" /> \
+             <a href=\"data:application/pdf;base64,JVBERg==\" download=\"&quot;report&quot;.pdf\">file</a> \
+             <a href=\"https://joplinapp.org\">web</a> \
+             <a href=\"data:application/octet-stream;base64,JVBERg==\" download=\"Evil\">evil</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn resource_links_are_replaced_by_paths() {
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        let image_id = "0123456789abcdef0123456789abcdef";
+        let missing_id = "fedcba9876543210fedcba9876543210";
+        let mut store = MemoryStore::new();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!(
+                "Title\n\n![image](:/{}) [file](:/{})\n\nid: {}\ntype_: 1\nencryption_applied: 0",
+                image_id, missing_id, note_id
+            ),
+        );
+        store.insert(
+            &format!("{}.md", image_id),
+            format!(
+                "image.png\n\nid: {}\nmime: image/png\nfile_extension: png\nsize: 2048\nencryption_blob_encrypted: 1\ntype_: 4\nencryption_applied: 0",
+                image_id
+            ),
+        );
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        let info = notebook.resource_info(image_id).unwrap();
+        assert_eq!(info.title.as_deref(), Some("image.png"));
+        assert_eq!(info.mime.as_deref(), Some("image/png"));
+        assert_eq!(info.size, Some(2048));
+        assert!(info.blob_encrypted);
+        assert!(notebook.resource_info(note_id).is_err());
+        assert_eq!(
+            notebook.read_note_with_resource_paths(note_id, "export/_resources/").unwrap(),
+            format!(
+                "![image](export/_resources/{}.png) [file](export/_resources/{})",
+                image_id, missing_id
+            )
+        );
+    }
+
+    #[test]
+    fn search_streaming_stops_early() {
+        let mut store = MemoryStore::new();
+        for (id, title, body) in [
+            ("a", "Groceries", "Buy milk"),
+            ("b", "Milk prices", "Too high"),
+            ("c", "Other", "Nothing"),
+        ] {
+            store.insert(
+                &format!("{}.md", id),
+                format!("{}\n\n{}\n\nid: {}\ntype_: 1\nencryption_applied: 0", title, body, id),
+            );
+        }
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        let results = notebook.search("MILK");
+        let note_ids: Vec<&str> = results.iter().map(|r| r.note_id.as_str()).collect();
+        assert_eq!(note_ids, vec!["a", "b"]);
+        assert_eq!(results[1].title.as_deref(), Some("Milk prices"));
+
+        let mut found: Vec<SearchResult> = Vec::new();
+        let ordered: Vec<&str> = notebook.notes_ordered().iter().map(|n| n.get_id()).collect();
+        assert_eq!(ordered, vec!["a", "b", "c"]);
+
+        notebook.search_streaming("milk", |result| {
+            found.push(result);
+            ControlFlow::Break(())
+        });
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].note_id, "a");
+    }
+
+    #[test]
+    fn note_stats_counts_the_body() {
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        let mut store = MemoryStore::new();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!("Title\n\nSchöne Grüße\nfrom here\n\nid: {}\ntype_: 1\nencryption_applied: 0", note_id),
+        );
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(
+            notebook.note_stats(note_id).unwrap(),
+            NoteStats {
+                chars: 22,
+                words: 4,
+                lines: 2
+            }
+        );
+        assert_eq!(notebook.total_words(), 4);
+    }
+
+    #[test]
+    fn multi_store_prefers_later_stores() {
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        let item = |body: &str| {
+            format!("Title\n\n{}\n\nid: {}\ntype_: 1\nencryption_applied: 0", body, note_id)
+        };
+        let mut first = encrypted_store();
+        first.insert(&format!("{}.md", note_id), item("Old"));
+        let mut second = MemoryStore::new();
+        second.insert(&format!("{}.md", note_id), item("New"));
+        let mut notebook =
+            JoplinNotebook::with_store(MultiStore::new(vec![first, second]), vec![PASSWORD]).unwrap();
+
+        assert_eq!(notebook.read_note(note_id).unwrap(), "New");
+        // Items and keys of the first store are still there
+        assert!(notebook.note_decrypt_stats("6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c").is_ok());
+    }
+
+    #[test]
+    fn health_check_reports_every_category() {
+        let mut store = encrypted_store();
+        let item = |id: &str, parent_id: &str, type_: u8, extra: &str| {
+            format!(
+                "Title\n\nid: {}\nparent_id: {}\ntype_: {}\nencryption_applied: 0{}",
+                id, parent_id, type_, extra
+            )
+        };
+        store.insert("folder.md", item("folder", "", 2, ""));
+        store.insert("fine.md", item("fine", "folder", 1, ""));
+        store.insert("orphan.md", item("orphan", "gone", 1, ""));
+        store.insert("time.md", item("time", "", 1, "\nuser_updated_time: yesterday"));
+        store.insert("broken.md", "not an item");
+        store.insert("locked.md", ENCRYPTED_FOLDER.replace(MASTER_KEY_ID, "00000000000000000000000000000000"));
+        let notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+
+        let report = notebook.health_check();
+        assert_eq!(report.unparsable_files, vec!["broken.md"]);
+        assert_eq!(report.missing_keys, vec!["locked"]);
+        assert!(report.decryption_failures.is_empty());
+        assert_eq!(report.malformed_timestamps, vec!["time"]);
+        assert_eq!(report.orphaned, vec!["orphan"]);
+        assert!(!report.is_healthy());
+    }
+
+    #[test]
+    fn metadata_only_skips_bodies_and_decryption() {
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        let folder_id = "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c";
+        let mut store = encrypted_store();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!("Title\n\nBody\n\nid: {}\ntype_: 1\nencryption_applied: 0", note_id),
+        );
+        // Without the password nothing could be decrypted anyway
+        let mut notebook = JoplinNotebookBuilder::new()
+            .metadata_only(true)
+            .build_with_store(store, vec![])
+            .unwrap();
+
+        match notebook.read_note(note_id) {
+            Err(JoplinReaderError::NoText) => {}
+            result => panic!("Expected no text, got {:?}", result),
+        }
+        assert_eq!(notebook.get_note(note_id).unwrap().get_content().title(), Some("Title"));
+
+        let (store, options) = (&notebook.store, &notebook.options);
+        let folder = notebook.notes.get_mut(folder_id).unwrap();
+        let properties = folder.read_properties_with(store, None, options).unwrap();
+        assert_eq!(properties.title(), None);
+        assert_eq!(properties.extra_properties().get("id").map(String::as_str), Some(folder_id));
+    }
+
+    #[test]
+    fn read_full_returns_all_properties() {
+        let folder_id = "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c";
+        let mut notebook = JoplinNotebook::with_store(encrypted_store(), vec![PASSWORD]).unwrap();
+
+        let properties = notebook.read_full(folder_id).unwrap();
+        assert_eq!(properties.title(), Some("Work"));
+        assert_eq!(properties.body(), None);
+        let title = properties.title().unwrap().as_ptr();
+        assert!(notebook.get_note(folder_id).unwrap().is_cached());
+        // Decrypting again would allocate a new title
+        assert_eq!(notebook.read_full(folder_id).unwrap().title().unwrap().as_ptr(), title);
+    }
+
+    #[test]
+    fn folder_title_is_decrypted() {
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        let mut store = encrypted_store();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!("Title\n\nBody\n\nid: {}\ntype_: 1\nencryption_applied: 0", note_id),
+        );
+        let mut notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+
+        assert_eq!(notebook.folder_title("6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c").unwrap(), "Work");
+        match notebook.folder_title(note_id) {
+            Err(JoplinReaderError::WrongItemType { .. }) => {}
+            other => panic!("Expected a note not to be a folder, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn preload_reads_into_the_cache() {
+        let folder_id = "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c";
+        let missing_id = "00000000000000000000000000000000";
+        let mut notebook = JoplinNotebook::with_store(encrypted_store(), vec![PASSWORD]).unwrap();
+        assert!(!notebook.get_note(folder_id).unwrap().is_cached());
+
+        let results = notebook.preload(&[folder_id, missing_id]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, folder_id);
+        assert!(results[0].1.is_ok());
+        assert!(matches!(results[1].1, Err(JoplinReaderError::NoteIdNotFound { .. })));
+        assert!(notebook.get_note(folder_id).unwrap().is_cached());
+    }
+
+    #[test]
+    fn passwords_without_comma_are_rejected() {
+        match JoplinNotebook::with_store(encrypted_store(), vec!["plaintext_password"]) {
+            Err(JoplinReaderError::InvalidFormat { message }) => {
+                assert_eq!(message, "password must be key_id,passphrase")
+            }
+            other => panic!("Expected an invalid password, got {:?}", other.map(|_| ())),
+        }
+        let password = format!("{},wrong_password", MASTER_KEY_ID);
+        match JoplinNotebook::with_store(encrypted_store(), vec![password.as_str()]) {
+            Err(JoplinReaderError::DecryptionError { .. }) => {}
+            other => panic!("Expected a wrong password, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn notebook_is_read_from_jex_archive() {
+        let folder_id = "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c";
+        let image_id = "0123456789abcdef0123456789abcdef";
+        let mut builder = tar::Builder::new(Vec::new());
+        let files: [(String, &[u8]); 3] = [
+            (format!("{}.md", MASTER_KEY_ID), MASTER_KEY.as_bytes()),
+            (format!("{}.md", folder_id), ENCRYPTED_FOLDER.as_bytes()),
+            (format!("resources/{}.png", image_id), &[0x89, b'P', b'N', b'G']),
+        ];
+        for (path, content) in files.iter() {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, *content).unwrap();
+        }
+        let archive = builder.into_inner().unwrap();
+
+        let mut notebook = JoplinNotebook::from_jex(archive.as_slice(), vec![PASSWORD]).unwrap();
+        assert!(notebook.load_errors().is_empty());
+        assert_eq!(notebook.folder_title(folder_id).unwrap(), "Work");
+        let resource = notebook.get_store().read_file(&format!("resources/{}.png", image_id));
+        assert_eq!(resource.unwrap(), vec![0x89, b'P', b'N', b'G']);
+    }
+
+    #[test]
+    fn titles_of_unreadable_notes_are_none() {
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        let mut store = encrypted_store();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!("Title\n\nBody\n\nid: {}\ntype_: 1\nencryption_applied: 0", note_id),
+        );
+        // The folder cannot be decrypted without the password, so it looks
+        // like a note without title
+        let mut notebook = JoplinNotebook::with_store(store.clone(), vec![]).unwrap();
+        assert_eq!(
+            notebook.titles(),
+            vec![
+                ("6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c".to_string(), None),
+                (note_id.to_string(), Some("Title".to_string())),
+            ]
+        );
+
+        let mut notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+        assert_eq!(notebook.titles(), vec![(note_id.to_string(), Some("Title".to_string()))]);
+    }
+
+    #[test]
+    fn duplicate_ids_are_reported() {
+        let mut store = MemoryStore::new();
+        store.insert("abc.txt", item("abc", "", 2));
+        store.insert("abc.md", item("abc", "", 1));
+        let notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(notebook.item_type("abc"), Some(&JoplinItemType::Note));
+        match notebook.load_errors() {
+            [(path, JoplinReaderError::DuplicateNoteId { id, path: loaded })] => {
+                assert_eq!((path.as_str(), id.as_str(), loaded.as_str()), ("abc.txt", "abc", "abc.md"));
+            }
+            other => panic!("Expected a duplicate id, got {:?}", other),
+        }
+        let report = notebook.health_check();
+        assert_eq!(report.duplicate_ids, vec!["abc.txt"]);
+        assert!(report.unparsable_files.is_empty());
+    }
+
+    #[test]
+    fn filter_notes_by_header() {
+        let mut store = encrypted_store();
+        store.insert("folder.md", item("folder", "", 2));
+        store.insert("a.md", item("a", "folder", 1));
+        store.insert("b.md", item("b", "", 1));
+        let notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+
+        let ids = |notes: Vec<&NoteInfo>| notes.iter().map(|n| n.get_id().to_string()).collect::<Vec<_>>();
+        assert_eq!(ids(notebook.filter_notes(|n| n.is_encrypted())), vec!["6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c"]);
+        assert_eq!(ids(notebook.filter_notes(|n| n.get_parent_id() == Some("folder"))), vec!["a"]);
+    }
+
+    #[test]
+    fn broken_resource_links_are_found() {
+        let image_id = "0123456789abcdef0123456789abcdef";
+        let missing_id = "fedcba9876543210fedcba9876543210";
+        let note = |id: &str, body: &str| format!("Title\n\n{}\n\nid: {}\ntype_: 1\nencryption_applied: 0", body, id);
+        let mut store = MemoryStore::new();
+        store.insert(
+            "a.md",
+            note("a", &format!("![image](:/{}) [file](:/{}) [again](:/{}) [b](:/b)", image_id, missing_id, missing_id)),
+        );
+        store.insert("b.md", note("b", &format!("![image](:/{})", image_id)));
+        store.insert(
+            &format!("{}.md", image_id),
+            format!("image.png\n\nid: {}\ntype_: 4\nencryption_applied: 0", image_id),
+        );
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(
+            notebook.broken_resource_links(),
+            vec![("a".to_string(), vec![missing_id.to_string()])]
+        );
+    }
+
+    #[test]
+    fn linked_notes_are_read() {
+        let note_id = "9a20a9e4d336de70cb6d22a58a3e673c";
+        let mut store = MemoryStore::new();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!("Title\n\nBody\n\nid: {}\ntype_: 1\nencryption_applied: 0", note_id),
+        );
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(notebook.read_linked_note(note_id).unwrap(), "Body");
+        assert_eq!(notebook.read_linked_note(&format!(":/{}", note_id)).unwrap(), "Body");
+        assert_eq!(notebook.read_linked_note(&format!(":/{}#heading", note_id)).unwrap(), "Body");
+        match notebook.read_linked_note(":/00000000000000000000000000000000") {
+            Err(JoplinReaderError::NoteIdNotFound { .. }) => {}
+            other => panic!("Expected the note not to be found, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn saved_searches_are_parsed() {
+        let mut store = MemoryStore::new();
+        store.insert(
+            "search.md",
+            "Todo\n\nid: search\nquery: type:todo iscompleted:0\ntype_: 7\nencryption_applied: 0",
+        );
+        store.insert(
+            "filter.md",
+            "Recent\n\nid: filter\nquery_pattern: updated:day-7\ntype_: 15\nencryption_applied: 0",
+        );
+        store.insert("note.md", item("note", "", 1));
+        let notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(
+            notebook.saved_searches(),
+            vec![
+                SavedSearch {
+                    id: "filter".to_string(),
+                    type_: JoplinItemType::SmartFilter,
+                    title: Some("Recent".to_string()),
+                    query: Some("updated:day-7".to_string()),
+                },
+                SavedSearch {
+                    id: "search".to_string(),
+                    type_: JoplinItemType::Search,
+                    title: Some("Todo".to_string()),
+                    query: Some("type:todo iscompleted:0".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn orphaned_notes_have_no_parent_folder() {
+        let mut store = MemoryStore::new();
+        store.insert("folder.md", item("folder", "", 2));
+        store.insert("top.md", item("top", "", 1));
+        store.insert("inside.md", item("inside", "folder", 1));
+        store.insert("missing.md", item("missing", "gone", 1));
+        store.insert("under_note.md", item("under_note", "top", 1));
+        let notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(notebook.orphaned_notes(), vec!["missing", "under_note"]);
+    }
+
+    #[test]
+    fn note_bundle_collects_tags_resources_and_folders() {
+        let note_id = "0123456789abcdef0123456789abcdef";
+        let resource_id = "1123456789abcdef0123456789abcdef";
+        let other_note_id = "2123456789abcdef0123456789abcdef";
+        let missing_id = "3123456789abcdef0123456789abcdef";
+        let mut store = encrypted_store();
+        store.insert("root.md", "Projects\n\nid: root\nparent_id: \ntype_: 2\nencryption_applied: 0");
+        store.insert("sub.md", "Alpha\n\nid: sub\nparent_id: root\ntype_: 2\nencryption_applied: 0");
+        store.insert(
+            &format!("{}.md", note_id),
+            format!(
+                "Plan\n\n![a](:/{}) [b](:/{}) [c](:/{})\n\nid: {}\nparent_id: sub\ntype_: 1\nencryption_applied: 0",
+                resource_id, other_note_id, missing_id, note_id
+            ),
+        );
+        store.insert(&format!("{}.md", resource_id), item(resource_id, "", 4));
+        store.insert(&format!("{}.md", other_note_id), item(other_note_id, "sub", 1));
+        store.insert("tag1.md", "work\n\nid: tag1\ntype_: 5\nencryption_applied: 0");
+        for (id, tag_id) in [("link1", "tag1"), ("link2", "gone")] {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "id: {}\nnote_id: {}\ntag_id: {}\ntype_: 6\nencryption_applied: 0",
+                    id, note_id, tag_id
+                ),
+            );
+        }
+        let mut notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+
+        let bundle = notebook.note_bundle(note_id).unwrap();
+        // Encrypted items are read once, and skipped when known not to be tags
+        let folder = notebook.get_note("6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c").unwrap();
+        assert_eq!(folder.effective_type(), Some(JoplinItemType::Folder));
+        assert_eq!(bundle.id, note_id);
+        assert_eq!(bundle.title.as_deref(), Some("Plan"));
+        assert!(bundle.body.starts_with("![a]"));
+        assert_eq!(bundle.properties.get("parent_id").map(String::as_str), Some("sub"));
+        let item = |id: &str, title: Option<&str>| BundleItem {
+            id: id.to_string(),
+            title: title.map(|title| title.to_string()),
+        };
+        assert_eq!(bundle.tags, vec![item("gone", None), item("tag1", Some("work"))]);
+        assert_eq!(bundle.resource_ids, vec![resource_id, missing_id]);
+        assert_eq!(
+            bundle.folder_path,
+            vec![item("root", Some("Projects")), item("sub", Some("Alpha"))]
+        );
+    }
+
+    #[test]
+    fn tags_match_case_insensitively_and_by_hierarchy() {
+        let mut store = MemoryStore::new();
+        let tags = [("tag1", "project"), ("tag2", "project/alpha"), ("tag3", "home/garden/beds")];
+        for (id, title) in tags {
+            store.insert(
+                &format!("{}.md", id),
+                format!("{}\n\nid: {}\ntype_: 5\nencryption_applied: 0", title, id),
+            );
+        }
+        let links = [("link1", "note1", "tag1"), ("link2", "note2", "tag2"), ("link3", "note3", "tag3")];
+        for (id, note_id, tag_id) in links {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "id: {}\nnote_id: {}\ntag_id: {}\ntype_: 6\nencryption_applied: 0",
+                    id, note_id, tag_id
+                ),
+            );
+        }
+        let notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(notebook.notes_with_tag("PROJECT", false), vec!["note1"]);
+        assert_eq!(notebook.notes_with_tag("project", true), vec!["note1", "note2"]);
+        assert_eq!(notebook.notes_with_tag("home", true), vec!["note3"]);
+        assert!(notebook.notes_with_tag("proj", true).is_empty());
+
+        let children = |titles: &[&str]| titles.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            notebook.tag_hierarchy(),
+            vec![
+                ("home".to_string(), children(&["home/garden"])),
+                ("home/garden".to_string(), children(&["home/garden/beds"])),
+                ("home/garden/beds".to_string(), children(&[])),
+                ("project".to_string(), children(&["project/alpha"])),
+                ("project/alpha".to_string(), children(&[])),
+            ]
+        );
+    }
+
+    #[test]
+    fn keys_can_be_added_after_loading() {
+        let folder_id = "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c";
+        let mut notebook = JoplinNotebook::with_store(encrypted_store(), vec![]).unwrap();
+        assert!(matches!(
+            notebook.read_full(folder_id),
+            Err(JoplinReaderError::NoEncryptionKey { .. })
+        ));
+
+        assert!(notebook.add_key("0123456789abcdef0123456789abcdef", "plaintext_password").is_err());
+        assert!(notebook.add_key(MASTER_KEY_ID, "wrong_password").is_err());
+        notebook.add_key(MASTER_KEY_ID, "plaintext_password").unwrap();
+        assert_eq!(notebook.folder_title(folder_id).unwrap(), "Work");
+    }
+
+    #[test]
+    fn notes_are_appended_to_the_buffer() {
+        let note_id = "0123456789abcdef0123456789abcdef";
+        let mut store = MemoryStore::new();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!("Title\n\nBody\n\nid: {}\ntype_: 1\nencryption_applied: 0", note_id),
+        );
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        let mut buf = String::from("> ");
+        notebook.read_note_into(note_id, &mut buf).unwrap();
+        assert_eq!(buf, "> Body");
+        assert!(notebook.read_note_into("1123456789abcdef0123456789abcdef", &mut buf).is_err());
+        assert_eq!(buf, "> Body");
+    }
+
+    #[test]
+    fn todos_are_ordered_by_due_date() {
+        let mut store = MemoryStore::new();
+        for (id, is_todo, due, completed) in [
+            ("late", 1, "1700000000000", "0"),
+            ("early", 1, "1600000000000", "0"),
+            ("undated", 1, "0", "0"),
+            ("done", 1, "1500000000000", "1650000000000"),
+            ("note", 0, "0", "0"),
+        ] {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "Title\n\nid: {}\nis_todo: {}\ntodo_due: {}\ntodo_completed: {}\ntype_: 1\nencryption_applied: 0",
+                    id, is_todo, due, completed
+                ),
+            );
+        }
+        store.insert("folder.md", item("folder", "", 2));
+        // Notes without their master key are skipped
+        store.insert("locked.md", ENCRYPTED_NOTE);
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(notebook.todos(false), vec!["early", "late", "undated"]);
+        assert_eq!(notebook.todos(true), vec!["done", "early", "late", "undated"]);
+        let done = notebook.get_note("done").unwrap().get_content();
+        assert_eq!(done.todo_completed().map(|t| t.and_utc().timestamp()), Some(1_650_000_000));
+    }
+
+    #[test]
+    fn content_hash_only_depends_on_title_and_body() {
+        let ids = [
+            "0123456789abcdef0123456789abcdef",
+            "1123456789abcdef0123456789abcdef",
+            "2123456789abcdef0123456789abcdef",
+            "3123456789abcdef0123456789abcdef",
+        ];
+        let mut store = MemoryStore::new();
+        for (id, title, body, time) in [
+            (ids[0], "Title", "Body", "2021-01-01T00:00:00.000Z"),
+            (ids[1], "Title", "Body", "2022-01-01T00:00:00.000Z"),
+            (ids[2], "Title", "Other", "2021-01-01T00:00:00.000Z"),
+            (ids[3], "TitleB", "ody", "2021-01-01T00:00:00.000Z"),
+        ] {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "{}\n\n{}\n\nid: {}\nupdated_time: {}\ntype_: 1\nencryption_applied: 0",
+                    title, body, id, time
+                ),
+            );
+        }
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        let hashes: Vec<u64> = ids.iter().map(|id| notebook.content_hash(id).unwrap()).collect();
+        assert_eq!(hashes[0], hashes[1]);
+        assert_ne!(hashes[0], hashes[2]);
+        assert_ne!(hashes[0], hashes[3]);
+    }
+
+    #[test]
+    fn only_items_with_the_id_prefix_are_loaded() {
+        let mut store = encrypted_store();
+        store.insert("a1.md", item("a1", "", 1));
+        store.insert("a2.md", item("a2", "", 1));
+        store.insert("b1.md", item("b1", "", 1));
+        let notebook = JoplinNotebook::builder()
+            .id_prefix("a")
+            .build_with_store(store, vec![PASSWORD])
+            .unwrap();
+
+        assert_eq!(notebook.iter().collect::<Vec<_>>(), vec!["a1", "a2"]);
+    }
+
+    #[test]
+    fn query_combines_all_conditions() {
+        let mut store = MemoryStore::new();
+        for (id, type_, time) in [
+            ("old", 1, "2020-01-01T00:00:00.000Z"),
+            ("new", 1, "2022-01-01T00:00:00.000Z"),
+            ("untagged", 1, "2022-01-01T00:00:00.000Z"),
+            ("folder", 2, "2022-01-01T00:00:00.000Z"),
+        ] {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "Title\n\nid: {}\nupdated_time: {}\ntype_: {}\nencryption_applied: 0",
+                    id, time, type_
+                ),
+            );
+        }
+        store.insert("tag1.md", "Work\n\nid: tag1\ntype_: 5\nencryption_applied: 0");
+        for (id, note_id) in [("link1", "old"), ("link2", "new"), ("link3", "folder")] {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "id: {}\nnote_id: {}\ntag_id: tag1\ntype_: 6\nencryption_applied: 0",
+                    id, note_id
+                ),
+            );
+        }
+        let notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+        let ids = |notes: Vec<&NoteInfo>| {
+            notes.iter().map(|note| note.get_id().to_string()).collect::<Vec<_>>()
+        };
+        let since = NaiveDateTime::parse_from_str("2021-01-01T00:00:00", "%Y-%m-%dT%H:%M:%S").unwrap();
+
+        assert_eq!(ids(notebook.query().with_tag("work").execute()), vec!["folder", "new", "old"]);
+        assert_eq!(
+            ids(notebook
+                .query()
+                .of_type(JoplinItemType::Note)
+                .updated_after(since)
+                .with_tag("work")
+                .execute()),
+            vec!["new"]
+        );
+        assert_eq!(
+            ids(notebook
+                .query()
+                .of_type(JoplinItemType::Note)
+                .filter(|note| note.get_id().starts_with('u'))
+                .execute()),
+            vec!["untagged"]
+        );
+    }
+
+    #[test]
+    fn raw_file_is_not_decrypted() {
+        let notebook = JoplinNotebook::with_store(encrypted_store(), vec![PASSWORD]).unwrap();
+
+        let file = notebook.raw_file("6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c").unwrap();
+        assert_eq!(file, ENCRYPTED_FOLDER.as_bytes());
+        assert!(notebook.raw_file("0123456789abcdef0123456789abcdef").is_err());
+    }
+
+    #[test]
+    fn markup_languages_are_counted() {
+        let mut store = MemoryStore::new();
+        for (id, markup_language) in [("a", "1"), ("b", "2"), ("c", "2")] {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "Title\n\nid: {}\nmarkup_language: {}\ntype_: 1\nencryption_applied: 0",
+                    id, markup_language
+                ),
+            );
+        }
+        store.insert("d.md", item("d", "", 1));
+        store.insert("folder.md", item("folder", "", 2));
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        let distribution = notebook.markup_distribution();
+        assert_eq!(distribution.len(), 2);
+        assert_eq!(distribution[&MarkupLanguage::Markdown], 2);
+        assert_eq!(distribution[&MarkupLanguage::Html], 2);
+    }
+
+    #[cfg(feature = "api-json")]
+    #[test]
+    fn notes_are_exported_in_the_api_schema() {
+        let note_id = "0123456789abcdef0123456789abcdef";
+        let mut store = encrypted_store();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!(
+                "Title\n\n  Body\n\nid: {}\ncreated_time: 2021-01-01T00:00:00.500Z\nis_todo: 1\ntodo_due: 1600000000000\nlatitude: 48.13743000\ntype_: 1\nencryption_applied: 0",
+                note_id
+            ),
+        );
+        let mut notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+
+        let note: serde_json::Value = serde_json::from_str(&notebook.to_api_json(note_id).unwrap()).unwrap();
+        assert_eq!(note["id"], note_id);
+        assert_eq!(note["title"], "Title");
+        assert_eq!(note["body"], "  Body");
+        assert_eq!(note["created_time"], 1_609_459_200_500_i64);
+        assert_eq!(note["is_todo"], 1);
+        assert_eq!(note["todo_due"], 1_600_000_000_000_i64);
+        assert_eq!(note["todo_completed"], 0);
+        assert_eq!(note["latitude"], 48.13743);
+        assert_eq!(note["author"], "");
+        assert_eq!(note.as_object().unwrap().len(), 30);
+        assert!(matches!(
+            notebook.to_api_json("6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c"),
+            Err(JoplinReaderError::WrongItemType {
+                found: JoplinItemType::Folder,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn command_items_are_not_notes() {
+        let command_id = "0123456789abcdef0123456789abcdef";
+        let mut store = MemoryStore::new();
+        store.insert(
+            &format!("{}.md", command_id),
+            format!(
+                "Synchronise\n\nid: {}\nname: synchronize\ntype_: 16\nencryption_applied: 0",
+                command_id
+            ),
+        );
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        assert_eq!(notebook.item_type(command_id), Some(&JoplinItemType::Command));
+        match notebook.read_note(command_id) {
+            Err(JoplinReaderError::WrongItemType { expected, found }) => {
+                assert_eq!(expected, JoplinItemType::Note);
+                assert_eq!(found, JoplinItemType::Command);
+            }
+            other => panic!("Expected a command not to be a note, got {:?}", other),
+        }
+        let properties = notebook.raw_properties(command_id).unwrap();
+        assert_eq!(properties.get("name").map(String::as_str), Some("synchronize"));
+        assert!(!properties.contains_key("body"));
+    }
+
+    #[cfg(feature = "tar")]
+    #[test]
+    fn export_to_tar_writes_notes_and_resources() {
+        use std::io::Read;
+
+        let note_id = "0123456789abcdef0123456789abcdef";
+        let resource_id = "1123456789abcdef0123456789abcdef";
+        // Loaded without the password, so the encrypted item cannot be read
+        let mut store = encrypted_store();
+        store.insert(
+            &format!("{}.md", note_id),
+            format!("Title\n\nBody\n\nid: {}\ntype_: 1\nencryption_applied: 0", note_id),
+        );
+        store.insert(
+            &format!("{}.md", resource_id),
+            format!(
+                "image.png\n\nid: {}\nfile_extension: png\ntype_: 4\nencryption_applied: 0",
+                resource_id
+            ),
+        );
+        store.insert(&format!(".resource/{}", resource_id), vec![0x89, 0x50, 0x4e, 0x47]);
+        store.insert("folder.md", item("folder", "", 2));
+        let mut notebook = JoplinNotebook::with_store(store, vec![]).unwrap();
+
+        let mut archive: Vec<u8> = Vec::new();
+        let failures = notebook.export_to_tar(&mut archive).unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, "6f2b8a1c9d3e4f5a7b8c9d0e1f2a3b4c");
+
+        let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+        for entry in tar::Archive::new(archive.as_slice()).entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry.path().unwrap().to_string_lossy().to_string();
+            let mut content: Vec<u8> = Vec::new();
+            entry.read_to_end(&mut content).unwrap();
+            entries.push((path, content));
+        }
+        assert_eq!(
+            entries,
+            vec![
+                (format!("notes/{}.md", note_id), b"# Title\n\nBody".to_vec()),
+                (format!("resources/{}.png", resource_id), vec![0x89, 0x50, 0x4e, 0x47]),
+            ]
+        );
+    }
+
+    #[test]
+    fn notes_are_found_by_property() {
+        let mut store = encrypted_store();
+        for (id, application, type_) in [
+            ("a", "net.cozic.joplin-mobile", 1),
+            ("b", "net.cozic.joplin-desktop", 1),
+            ("c", "net.cozic.joplin-mobile", 1),
+            ("folder", "net.cozic.joplin-mobile", 2),
+        ] {
+            store.insert(
+                &format!("{}.md", id),
+                format!(
+                    "Title\n\nid: {}\nsource_application: {}\ntype_: {}\nencryption_applied: 0",
+                    id, application, type_
+                ),
+            );
+        }
+        store.insert("1b2c3d4e5f60718293a4b5c6d7e8f901.md", ENCRYPTED_NOTE);
+        let mut notebook = JoplinNotebook::with_store(store, vec![PASSWORD]).unwrap();
+
+        assert_eq!(
+            notebook.find_by_property("source_application", "net.cozic.joplin-mobile"),
+            vec!["a", "c"]
+        );
+        // Encrypted items are decrypted, but only notes match
+        assert_eq!(
+            notebook.find_by_property("title", "Percent"),
+            vec!["1b2c3d4e5f60718293a4b5c6d7e8f901"]
+        );
+        assert!(notebook.get_note("1b2c3d4e5f60718293a4b5c6d7e8f901").unwrap().is_cached());
+        assert!(notebook.find_by_property("title", "Work").is_empty());
+        assert!(notebook.find_by_property("missing", "").is_empty());
+    }
+
+    #[test]
+    fn least_recently_read_content_is_dropped() {
+        let mut store = MemoryStore::new();
+        let ids = ["a".repeat(32), "b".repeat(32), "c".repeat(32)];
+        for id in ids.iter() {
+            store.insert(
+                &format!("{}.md", id),
+                format!("Title\n\nBody {}\n\nid: {}\ntype_: 1\nencryption_applied: 0", id, id),
+            );
+        }
+        let mut notebook = JoplinNotebook::builder()
+            .max_cached_notes(2)
+            .build_with_store(store, vec![])
+            .unwrap();
+        assert_eq!(notebook.get_max_cached_notes(), Some(2));
+
+        notebook.read_note(&ids[0]).unwrap();
+        notebook.read_note(&ids[1]).unwrap();
+        // Reading `a` again makes `b` the least recently read
+        notebook.read_note(&ids[0]).unwrap();
+        assert_eq!(notebook.read_note(&ids[2]).unwrap(), format!("Body {}", ids[2]));
+        let cached: Vec<bool> = ids.iter().map(|id| notebook.get_note(id).unwrap().is_cached()).collect();
+        assert_eq!(cached, vec![true, false, true]);
+        assert!(notebook.get_note(&ids[1]).unwrap().get_content().body().is_none());
+
+        // Dropped content is read again when needed
+        assert_eq!(notebook.read_note(&ids[1]).unwrap(), format!("Body {}", ids[1]));
+        // Bulk reads drop the content while reading, but still see all notes
+        assert_eq!(notebook.total_words(), 6);
+        let titles = notebook.titles();
+        assert!(titles.iter().all(|(_, title)| title.as_deref() == Some("Title")));
+        assert_eq!(titles.len(), 3);
+        let cached = ids.iter().filter(|id| notebook.get_note(id).unwrap().is_cached()).count();
+        assert_eq!(cached, 2);
     }
 }
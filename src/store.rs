@@ -0,0 +1,181 @@
+use crate::JoplinReaderError;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Abstracts the access to the files of a Joplin data folder, so items can be
+/// read from places other than the local filesystem (e.g. a cloud bucket, or
+/// memory when running in WASM).
+/// Paths are always relative to the root of the data folder.
+pub trait NoteStore {
+    /// Returns the full content of the file at `path`, or
+    /// [`JoplinReaderError::FileNotFound`] if there is no such file.
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, JoplinReaderError>;
+
+    /// Lists the paths of all files in the data folder.
+    fn list(&self) -> Result<Vec<String>, JoplinReaderError>;
+
+    /// Returns when the file at `path` was last modified, if the store knows.
+    /// Used to decide whether an already read item needs to be read again.
+    fn modified(&self, _path: &str) -> Option<SystemTime> {
+        None
+    }
+}
+
+/// Default [`NoteStore`] which reads from a folder on the local filesystem.
+#[derive(Debug, Clone, Default)]
+pub struct FsStore {
+    root: PathBuf,
+}
+
+impl FsStore {
+    pub fn new<P: AsRef<Path>>(root: P) -> FsStore {
+        FsStore {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl NoteStore for FsStore {
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, JoplinReaderError> {
+        match fs::read(self.root.join(path)) {
+            Ok(content) => Ok(content),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(JoplinReaderError::FileNotFound {
+                path: path.to_string(),
+            }),
+            Err(_) => Err(JoplinReaderError::FileReadError {
+                message: "Failed to open file".to_string(),
+            }),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>, JoplinReaderError> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(d) => d,
+            Err(e) => {
+                let path = self.root.to_string_lossy().to_string();
+                return Err(match e.kind() {
+                    ErrorKind::NotFound => JoplinReaderError::FolderNotFound { path },
+                    ErrorKind::PermissionDenied => {
+                        JoplinReaderError::FolderPermissionDenied { path }
+                    }
+                    _ if self.root.is_file() => JoplinReaderError::NotAFolder { path },
+                    _ => JoplinReaderError::FolderReadError,
+                });
+            }
+        };
+        let mut paths: Vec<String> = Vec::new();
+        for entry in entries {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(_) => return Err(JoplinReaderError::FolderReadError),
+            };
+            if path.is_file() {
+                if let Some(file_name) = path.file_name().and_then(|f| f.to_str()) {
+                    paths.push(file_name.to_string());
+                }
+            }
+        }
+
+        Ok(paths)
+    }
+
+    fn modified(&self, path: &str) -> Option<SystemTime> {
+        fs::metadata(self.root.join(path)).ok()?.modified().ok()
+    }
+}
+
+/// [`NoteStore`] which holds all files in memory.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryStore {
+    files: HashMap<String, Vec<u8>>,
+    modified: HashMap<String, SystemTime>,
+}
+
+impl MemoryStore {
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// Adds a file, replacing any previous file at the same `path`.
+    pub fn insert(&mut self, path: &str, content: impl Into<Vec<u8>>) {
+        self.files.insert(path.to_string(), content.into());
+        self.modified.insert(path.to_string(), SystemTime::now());
+    }
+}
+
+impl NoteStore for MemoryStore {
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, JoplinReaderError> {
+        match self.files.get(path) {
+            Some(content) => Ok(content.clone()),
+            None => Err(JoplinReaderError::FileNotFound {
+                path: path.to_string(),
+            }),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<String>, JoplinReaderError> {
+        Ok(self.files.keys().cloned().collect())
+    }
+
+    fn modified(&self, path: &str) -> Option<SystemTime> {
+        self.modified.get(path).copied()
+    }
+}
+
+/// [`NoteStore`] which merges several stores, e.g. data folders split across
+/// multiple sync directories. If a file exists in more than one store, the
+/// one of the later store wins.
+#[derive(Debug, Clone, Default)]
+pub struct MultiStore<S: NoteStore = FsStore> {
+    stores: Vec<S>,
+}
+
+impl<S: NoteStore> MultiStore<S> {
+    pub fn new(stores: Vec<S>) -> MultiStore<S> {
+        MultiStore { stores }
+    }
+
+    pub fn get_stores(&self) -> &[S] {
+        &self.stores
+    }
+}
+
+impl<S: NoteStore> NoteStore for MultiStore<S> {
+    fn read_file(&self, path: &str) -> Result<Vec<u8>, JoplinReaderError> {
+        let mut error = JoplinReaderError::FileNotFound {
+            path: path.to_string(),
+        };
+        for store in self.stores.iter().rev() {
+            match store.read_file(path) {
+                Ok(content) => return Ok(content),
+                // The file may still be in one of the other stores
+                Err(JoplinReaderError::FileNotFound { .. }) => {}
+                Err(e) => error = e,
+            }
+        }
+        Err(error)
+    }
+
+    fn list(&self) -> Result<Vec<String>, JoplinReaderError> {
+        let mut paths: Vec<String> = Vec::new();
+        for store in self.stores.iter() {
+            paths.extend(store.list()?);
+        }
+        paths.sort_unstable();
+        paths.dedup();
+
+        Ok(paths)
+    }
+
+    fn modified(&self, path: &str) -> Option<SystemTime> {
+        self.stores.iter().rev().find_map(|store| store.modified(path))
+    }
+}
@@ -0,0 +1,75 @@
+use crate::store::{FsStore, NoteStore};
+use crate::JoplinReaderError;
+
+use std::path::Path;
+
+use serde_json::{Map, Value};
+
+/// Content of the `info.json` of a Joplin sync target, which describes the
+/// format of the items in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncInfo {
+    version: u64,
+    fields: Map<String, Value>,
+}
+
+impl SyncInfo {
+    /// Version of the sync target format
+    pub fn get_version(&self) -> u64 {
+        self.version
+    }
+
+    /// All fields of the `info.json`, e.g. `e2ee` or `masterKeys`
+    pub fn get_fields(&self) -> &Map<String, Value> {
+        &self.fields
+    }
+}
+
+/// Reads the `info.json` of the Joplin data folder `folder`.
+pub fn read_sync_info<P: AsRef<Path>>(folder: P) -> Result<SyncInfo, JoplinReaderError> {
+    read_sync_info_from_store(&FsStore::new(folder))
+}
+
+/// Same as [`read_sync_info`], but reads from a [`NoteStore`].
+pub fn read_sync_info_from_store<S: NoteStore>(store: &S) -> Result<SyncInfo, JoplinReaderError> {
+    let file = store.read_file("info.json")?;
+    let fields = match serde_json::from_slice::<Value>(&file) {
+        Ok(Value::Object(fields)) => fields,
+        _ => {
+            return Err(JoplinReaderError::InvalidFormat {
+                message: "`info.json` is not a JSON object".to_string(),
+            })
+        }
+    };
+    let version = match fields.get("version").and_then(|v| v.as_u64()) {
+        Some(version) => version,
+        None => {
+            return Err(JoplinReaderError::InvalidFormat {
+                message: "No `version` specified in `info.json`".to_string(),
+            })
+        }
+    };
+
+    Ok(SyncInfo { version, fields })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::MemoryStore;
+
+    #[test]
+    fn reads_version_and_fields() {
+        let mut store = MemoryStore::new();
+        store.insert(
+            "info.json",
+            r#"{"version":3,"e2ee":{"value":true,"updatedTime":0},"masterKeys":[]}"#,
+        );
+        let info = read_sync_info_from_store(&store).unwrap();
+        assert_eq!(info.get_version(), 3);
+        assert!(info.get_fields().contains_key("e2ee"));
+
+        store.insert("info.json", r#"{"e2ee":{}}"#);
+        assert!(read_sync_info_from_store(&store).is_err());
+    }
+}